@@ -0,0 +1,19 @@
+use std::env::var;
+use std::path::Path;
+use wayland_scanner::{generate_code, Side};
+
+/// Protocols that are not part of the wayland-protocols release we depend on
+static PROTOCOLS: &[&str] = &["fractional-scale-v1", "kde-blur"];
+
+fn main() {
+    let out_dir = var("OUT_DIR").unwrap();
+    for name in PROTOCOLS {
+        let xml = format!("protocols/{}.xml", name);
+        println!("cargo:rerun-if-changed={}", xml);
+        generate_code(
+            &xml,
+            Path::new(&out_dir).join(format!("{}_client_api.rs", name)),
+            Side::Client,
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+use xdg::BaseDirectories;
+
+use crate::selection::Element;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedAlias {
+    name: String,
+    value: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Collects aliases and functions of the users shell.
+///
+/// If `alias_file` is set, only that file is parsed. Otherwise `$SHELL` is started
+/// in interactive mode to list them, which can be slow depending on the rc files,
+/// so the result is cached until one of the rc files changes.
+pub fn load(alias_file: Option<&Path>) -> io::Result<Vec<Element>> {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_owned());
+
+    if let Some(alias_file) = alias_file {
+        let reader = BufReader::new(File::open(alias_file)?);
+        let mut res = Vec::new();
+        for line in reader.lines() {
+            if let Some((name, Some(body))) = parse_alias(&line?) {
+                res.push(element(&shell, name, &body, Some(body.clone())));
+            }
+        }
+        return Ok(res);
+    }
+
+    let xdg_dirs = BaseDirectories::with_prefix("kickoff")?;
+    let cache_name = format!("aliases-{}.csv", shell_name(&shell));
+    if let Some(cache) = xdg_dirs.find_cache_file(&cache_name) {
+        if is_fresh(&cache, &shell) {
+            match read_cache(&cache) {
+                Ok(res) => return Ok(res),
+                Err(e) => warn!("Failed to read alias cache: {}", e),
+            }
+        }
+    }
+
+    let res = dump(&shell);
+    let cache = xdg_dirs.place_cache_file(&cache_name)?;
+    if let Err(e) = write_cache(&cache, &res) {
+        warn!("Failed to write alias cache: {}", e);
+    }
+    Ok(res)
+}
+
+fn shell_name(shell: &str) -> &str {
+    Path::new(shell)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(shell)
+}
+
+fn rc_files(shell: &str) -> Vec<PathBuf> {
+    let home = match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => return Vec::new(),
+    };
+    match shell_name(shell) {
+        "bash" => vec![home.join(".bashrc"), home.join(".bash_aliases")],
+        "zsh" => {
+            let zdotdir = env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or(home);
+            vec![zdotdir.join(".zshrc"), zdotdir.join(".zshenv")]
+        }
+        "fish" => {
+            let config = BaseDirectories::with_prefix("fish")
+                .map(|dirs| dirs.get_config_home())
+                .unwrap_or_else(|_| home.join(".config/fish"));
+            vec![config.join("config.fish"), config.join("functions")]
+        }
+        _ => vec![home.join(".profile")],
+    }
+}
+
+/// The cache is valid as long as it is newer than every rc file of the shell
+fn is_fresh(cache: &Path, shell: &str) -> bool {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    let cache_modified = match modified(cache) {
+        Some(time) => time,
+        None => return false,
+    };
+    rc_files(shell)
+        .iter()
+        .filter_map(|path| modified(path))
+        .all(|time: SystemTime| time < cache_modified)
+}
+
+fn read_cache(cache: &Path) -> Result<Vec<Element>, csv::Error> {
+    let mut rdr = csv::Reader::from_path(cache)?;
+    rdr.deserialize()
+        .map(|record| {
+            let record: CachedAlias = record?;
+            Ok(Element {
+                name: record.name,
+                value: record.value,
+                description: record.description,
+                base_score: 0,
+            })
+        })
+        .collect()
+}
+
+fn write_cache(cache: &Path, elements: &[Element]) -> Result<(), csv::Error> {
+    let mut wtr = csv::Writer::from_path(cache)?;
+    for elem in elements {
+        wtr.serialize(CachedAlias {
+            name: elem.name.clone(),
+            value: elem.value.clone(),
+            description: elem.description.clone(),
+        })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Starts the shell interactively and lists all aliases and functions
+fn dump(shell: &str) -> Vec<Element> {
+    let functions = match shell_name(shell) {
+        "bash" => "compgen -A function",
+        "zsh" => "print -rl -- ${(k)functions}",
+        "fish" => "functions",
+        _ => "",
+    };
+    let output = Command::new(shell)
+        .args(["-i", "-c", &format!("alias; {}", functions)])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Failed to list aliases of {}: {}", shell, e);
+            return Vec::new();
+        }
+    };
+
+    let mut res: Vec<Element> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_alias)
+        // functions starting with an underscore are usually completion helpers
+        .filter(|(name, _)| !name.starts_with('_'))
+        .map(|(name, body)| element(shell, name, name, body))
+        .collect();
+    res.sort();
+    res.dedup_by(|a, b| a.name == b.name);
+    res
+}
+
+/// Parses a single alias definition in the formats printed by common shells:
+/// `alias name='value'` (bash), `name=value` (zsh), `alias name value` (fish)
+/// or a bare function name.
+fn parse_alias(line: &str) -> Option<(&str, Option<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("alias ").unwrap_or(line).trim_start();
+    let split = line.find(|c: char| c == '=' || c.is_whitespace());
+    let (name, body) = match split {
+        Some(i) => (&line[..i], Some(unquote(line[i + 1..].trim()))),
+        None => (line, None),
+    };
+    if name.is_empty() || name.starts_with('-') {
+        None
+    } else {
+        Some((name, body))
+    }
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2
+        && ((value.starts_with('\'') && value.ends_with('\''))
+            || (value.starts_with('"') && value.ends_with('"')))
+    {
+        value[1..value.len() - 1].replace("'\\''", "'")
+    } else {
+        value.to_owned()
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Wraps `command` so that it is executed by the users shell with its rc files loaded,
+/// the body of an alias is shown as its description
+fn element(shell: &str, name: &str, command: &str, body: Option<String>) -> Element {
+    Element {
+        name: name.to_owned(),
+        value: format!("{} -i -c {}", quote(shell), quote(command)),
+        description: body,
+        base_score: 0,
+    }
+}
+
+/// Aliases replace elements of the same name from other sources
+pub fn shadow(elements: &mut Vec<Element>, aliases: Vec<Element>) {
+    let names: HashSet<String> = aliases.iter().map(|a| a.name.clone()).collect();
+    elements.retain(|e| !names.contains(&e.name));
+    elements.extend(aliases);
+}
@@ -0,0 +1,101 @@
+use image::{imageops, RgbaImage};
+use log::*;
+
+use crate::color::Color;
+use crate::config::{BackgroundMode, ColorConfig, StyleConfig};
+use crate::draw;
+
+/// Background color, image and overlay, composed once per window size
+pub struct Background {
+    color: Color,
+    overlay: Color,
+    image: Option<RgbaImage>,
+    mode: BackgroundMode,
+    opacity: f32,
+    cache: Option<RgbaImage>,
+}
+
+impl Background {
+    /// Loads the background image, which is left out if it can not be read
+    pub fn load(style: &StyleConfig, colors: &ColorConfig) -> Self {
+        let image = style
+            .background_image
+            .as_ref()
+            .and_then(|path| match image::open(path) {
+                Ok(image) => Some(image.into_rgba8()),
+                Err(e) => {
+                    warn!("Failed to load background {}: {}", path.display(), e);
+                    None
+                }
+            });
+        Background {
+            color: colors.background.clone(),
+            overlay: colors.overlay.clone(),
+            image,
+            mode: style.background_mode,
+            opacity: style.background_opacity.clamp(0., 1.),
+            cache: None,
+        }
+    }
+
+    /// Whether the background hides everything behind the window
+    pub fn is_opaque(colors: &ColorConfig) -> bool {
+        colors.background.3 == 255
+    }
+
+    /// A new frame of the given size with the background drawn
+    pub fn render(&mut self, width: u32, height: u32) -> RgbaImage {
+        match &self.cache {
+            Some(cache) if cache.dimensions() == (width, height) => cache.clone(),
+            _ => {
+                let frame = self.compose(width, height);
+                self.cache = Some(frame.clone());
+                frame
+            }
+        }
+    }
+
+    fn compose(&self, width: u32, height: u32) -> RgbaImage {
+        let mut frame = RgbaImage::from_pixel(width, height, self.color.to_rgba());
+        if let Some(image) = &self.image {
+            let (image_width, image_height) = image.dimensions();
+            let scale = match self.mode {
+                BackgroundMode::Fill => f32::max(
+                    width as f32 / image_width as f32,
+                    height as f32 / image_height as f32,
+                ),
+                BackgroundMode::Fit => f32::min(
+                    width as f32 / image_width as f32,
+                    height as f32 / image_height as f32,
+                ),
+                BackgroundMode::Center | BackgroundMode::Tile => 1.,
+            };
+            let mut layer = if scale == 1. {
+                image.clone()
+            } else {
+                imageops::resize(
+                    image,
+                    ((image_width as f32 * scale).round() as u32).max(1),
+                    ((image_height as f32 * scale).round() as u32).max(1),
+                    imageops::FilterType::Triangle,
+                )
+            };
+            if self.opacity < 1. {
+                for pixel in layer.pixels_mut() {
+                    pixel[3] = (pixel[3] as f32 * self.opacity).round() as u8;
+                }
+            }
+
+            if self.mode == BackgroundMode::Tile {
+                imageops::tile(&mut frame, &layer);
+            } else {
+                // Centered, cropping what does not fit
+                let x = (width as i64 - layer.width() as i64) / 2;
+                let y = (height as i64 - layer.height() as i64) / 2;
+                imageops::overlay(&mut frame, &layer, x, y);
+            }
+        }
+        draw::fill_rect(&mut frame, 0, 0, width, height, &self.overlay);
+        frame
+    }
+}
@@ -49,7 +49,7 @@ impl<'de> Visitor<'de> for ColorVisitor {
         if let Ok(c) = c {
             Ok(Color::from(c))
         } else {
-            Err(de::Error::custom(""))
+            Err(de::Error::custom(format!("invalid color '{}'", value)))
         }
     }
 }
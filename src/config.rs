@@ -1,12 +1,18 @@
 use crate::color::Color;
-use crate::keybinds::{KeyCombo, Modifiers};
+use crate::history::BackendKind;
+use crate::keybinds::{KeyCombo, KeySequence, Modifiers};
+use crate::outputs::FOCUSED;
+use crate::theme;
 use smithay_client_toolkit::seat::keyboard::{keysyms, ModifiersState};
-use std::fmt::Debug;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Debug};
 use std::fs::{read_to_string, write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
 use xdg::BaseDirectories;
 
-use serde::Deserialize;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
 
 #[derive(Deserialize, Clone)]
 #[serde(default)]
@@ -15,97 +21,624 @@ pub struct ColorConfig {
     pub text: Color,
     pub text_query: Color,
     pub text_selected: Color,
+    pub query_selection: Color,
     pub prompt: Color,
+    pub border: Color,
+    pub separator: Color,
+    pub selection_background: Color,
+    pub query_background: Color,
+    /// Drawn over the background image to dim or tint it
+    pub overlay: Color,
+    pub description: Color,
+    /// Behind the result under the pointer
+    pub hover_background: Color,
+    pub scrollbar: Color,
+    /// Number of matches next to the query
+    pub counter: Color,
+    /// Problems with the config, shown below the results
+    pub error: Color,
+}
+
+/// Shapes and spacing, sizes are in pixels unless noted otherwise
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StyleConfig {
+    pub border_width: u32,
+    pub border_radius: f32,
+    /// Height of a line as a multiple of the font size
+    pub line_height: f32,
+    /// Space between results
+    pub row_spacing: u32,
+    /// Space between the edge of the query box or a selected row and its text
+    pub row_padding: u32,
+    pub selection_radius: f32,
+    /// Width of the line between query and results
+    pub separator_width: u32,
+    pub query_radius: f32,
+    pub background_image: Option<PathBuf>,
+    pub background_mode: BackgroundMode,
+    pub background_opacity: f32,
+    /// Size of descriptions as a multiple of the font size
+    pub description_size: f32,
+    pub description_position: DescriptionPosition,
+    /// Shows which part of the results is visible when they do not fit the window
+    pub scrollbar: bool,
+    pub scrollbar_width: u32,
+    /// Shows the number of matches and of all results next to the query, like `12/340`
+    pub counter: bool,
+    /// Numbers next to the visible results, for the quick select keybindings
+    pub number_hints: bool,
+}
+
+/// Where the description of a result is shown in the vertical layout
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DescriptionPosition {
+    /// On the same line after the name
+    Beside,
+    /// On its own line under the name
+    Below,
+    Hidden,
+}
+
+/// How the background image is scaled to the window
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundMode {
+    /// Covers the whole window, cropping the image
+    Fill,
+    /// Shows the whole image, leaving the rest of the window empty
+    Fit,
+    /// Unscaled in the center
+    Center,
+    /// Repeated from the top left corner
+    Tile,
+}
+
+/// How keys edit the query on top of the keybindings
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditingMode {
+    Default,
+    /// Modal editing with insert and normal mode
+    Vi,
+    /// Readline style shortcuts like ctrl+a and alt+f
+    Emacs,
 }
 
 #[derive(Deserialize, Clone, Debug)]
 #[serde(default)]
 pub struct KeybindingsConfig {
-    pub delete: Vec<KeyCombo>,
-    pub delete_word: Vec<KeyCombo>,
-    pub execute: Vec<KeyCombo>,
-    pub paste: Vec<KeyCombo>,
-    pub complete: Vec<KeyCombo>,
-    pub nav_up: Vec<KeyCombo>,
-    pub nav_down: Vec<KeyCombo>,
-    pub exit: Vec<KeyCombo>,
+    pub mode: EditingMode,
+    /// Milliseconds to wait for the next key of a sequence like `ctrl+x ctrl+c`
+    pub chord_timeout: u64,
+    pub delete: Vec<KeySequence>,
+    pub delete_word: Vec<KeySequence>,
+    pub delete_forward: Vec<KeySequence>,
+    pub kill_to_end: Vec<KeySequence>,
+    pub kill_to_start: Vec<KeySequence>,
+    pub cursor_left: Vec<KeySequence>,
+    pub cursor_right: Vec<KeySequence>,
+    pub cursor_word_left: Vec<KeySequence>,
+    pub cursor_word_right: Vec<KeySequence>,
+    pub cursor_home: Vec<KeySequence>,
+    pub cursor_end: Vec<KeySequence>,
+    pub select_left: Vec<KeySequence>,
+    pub select_right: Vec<KeySequence>,
+    pub select_word_left: Vec<KeySequence>,
+    pub select_word_right: Vec<KeySequence>,
+    pub select_home: Vec<KeySequence>,
+    pub select_end: Vec<KeySequence>,
+    pub select_all: Vec<KeySequence>,
+    pub copy: Vec<KeySequence>,
+    pub cut: Vec<KeySequence>,
+    pub copy_element: Vec<KeySequence>,
+    pub undo: Vec<KeySequence>,
+    pub redo: Vec<KeySequence>,
+    pub execute: Vec<KeySequence>,
+    pub paste: Vec<KeySequence>,
+    pub complete: Vec<KeySequence>,
+    pub nav_up: Vec<KeySequence>,
+    pub nav_down: Vec<KeySequence>,
+    pub nav_left: Vec<KeySequence>,
+    pub nav_right: Vec<KeySequence>,
+    pub page_up: Vec<KeySequence>,
+    pub page_down: Vec<KeySequence>,
+    pub first: Vec<KeySequence>,
+    pub last: Vec<KeySequence>,
+    /// The n-th combination executes the n-th visible result
+    pub quick_select: Vec<KeySequence>,
+    #[serde(deserialize_with = "custom_bindings")]
+    pub custom: Vec<(KeySequence, CustomCommand)>,
+    pub exit: Vec<KeySequence>,
+}
+
+impl KeybindingsConfig {
+    /// Every action with its key sequences, by the name used in the config
+    fn actions_mut(&mut self) -> [(&'static str, &mut Vec<KeySequence>); 36] {
+        [
+            ("delete", &mut self.delete),
+            ("delete_word", &mut self.delete_word),
+            ("delete_forward", &mut self.delete_forward),
+            ("kill_to_end", &mut self.kill_to_end),
+            ("kill_to_start", &mut self.kill_to_start),
+            ("cursor_left", &mut self.cursor_left),
+            ("cursor_right", &mut self.cursor_right),
+            ("cursor_word_left", &mut self.cursor_word_left),
+            ("cursor_word_right", &mut self.cursor_word_right),
+            ("cursor_home", &mut self.cursor_home),
+            ("cursor_end", &mut self.cursor_end),
+            ("select_left", &mut self.select_left),
+            ("select_right", &mut self.select_right),
+            ("select_word_left", &mut self.select_word_left),
+            ("select_word_right", &mut self.select_word_right),
+            ("select_home", &mut self.select_home),
+            ("select_end", &mut self.select_end),
+            ("select_all", &mut self.select_all),
+            ("copy", &mut self.copy),
+            ("cut", &mut self.cut),
+            ("copy_element", &mut self.copy_element),
+            ("undo", &mut self.undo),
+            ("redo", &mut self.redo),
+            ("execute", &mut self.execute),
+            ("paste", &mut self.paste),
+            ("complete", &mut self.complete),
+            ("nav_up", &mut self.nav_up),
+            ("nav_down", &mut self.nav_down),
+            ("nav_left", &mut self.nav_left),
+            ("nav_right", &mut self.nav_right),
+            ("page_up", &mut self.page_up),
+            ("page_down", &mut self.page_down),
+            ("first", &mut self.first),
+            ("last", &mut self.last),
+            ("quick_select", &mut self.quick_select),
+            ("exit", &mut self.exit),
+        ]
+    }
+
+    /// Adds the readline style bindings of the emacs mode to the defaults, taking their keys
+    /// from other default actions. Actions set in `table` to something other than their
+    /// default, which the shipped config spells out, are left as configured.
+    fn emacs(&mut self, table: Option<&Table>) {
+        let is_set = |name: &str| table.is_some_and(|table| table.contains_key(name));
+        let mut defaults = KeybindingsConfig::default();
+        let preset = [
+            ("cursor_home", ctrl(keysyms::XKB_KEY_a)),
+            ("cursor_end", ctrl(keysyms::XKB_KEY_e)),
+            ("cursor_left", ctrl(keysyms::XKB_KEY_b)),
+            ("cursor_right", ctrl(keysyms::XKB_KEY_f)),
+            ("nav_up", ctrl(keysyms::XKB_KEY_p)),
+            ("nav_down", ctrl(keysyms::XKB_KEY_n)),
+            ("delete_forward", ctrl(keysyms::XKB_KEY_d)),
+            ("kill_to_end", ctrl(keysyms::XKB_KEY_k)),
+            ("kill_to_start", ctrl(keysyms::XKB_KEY_u)),
+            ("delete_word", ctrl(keysyms::XKB_KEY_w)),
+            ("exit", ctrl(keysyms::XKB_KEY_g)),
+            ("cursor_word_left", alt(keysyms::XKB_KEY_b)),
+            ("cursor_word_right", alt(keysyms::XKB_KEY_f)),
+        ];
+        for ((name, sequences), (_, default)) in
+            self.actions_mut().into_iter().zip(defaults.actions_mut())
+        {
+            if is_set(name) && sequences != default {
+                continue;
+            }
+            sequences.retain(|sequence| !preset.iter().any(|(_, keys)| keys == sequence));
+            sequences.extend(
+                preset
+                    .iter()
+                    .filter(|(action, _)| *action == name)
+                    .map(|(_, keys)| keys.clone()),
+            );
+        }
+    }
+
+    /// Reports key sequences bound to more than one action in `table`, the keybindings
+    /// section of the config, and drops default bindings of keys taken by other actions
+    fn check(
+        &mut self,
+        table: Option<&Table>,
+        sources: &[Source],
+        profile: Option<&str>,
+    ) -> Result<(), String> {
+        let is_set = |name: &str| table.is_some_and(|table| table.contains_key(name));
+        let mut taken: HashMap<KeySequence, &str> = HashMap::new();
+        let custom: Vec<KeySequence> = self.custom.iter().map(|(keys, _)| keys.clone()).collect();
+        let mut actions = self.actions_mut();
+        let configured = actions
+            .iter()
+            .filter(|(name, _)| is_set(name))
+            .flat_map(|(name, sequences)| sequences.iter().map(move |sequence| (*name, sequence)));
+        for (name, sequence) in configured.chain(custom.iter().map(|sequence| ("custom", sequence)))
+        {
+            match taken.insert(sequence.clone(), name) {
+                Some(other) if other != name => {
+                    // The last source binding it is the one in effect
+                    let line = |name| {
+                        let found = sources.iter().rev().find_map(|source| {
+                            binding_line(&source.content, profile, name, sequence)
+                                .map(|line| source.location(line))
+                        });
+                        match found {
+                            Some(location) => format!("{} ({})", name, location),
+                            None => name.to_owned(),
+                        }
+                    };
+                    return Err(format!(
+                        "Keybinding '{}' is used for both {} and {}",
+                        sequence,
+                        line(other),
+                        line(name)
+                    ));
+                }
+                _ => {}
+            }
+        }
+        for (name, sequences) in actions.iter_mut() {
+            // Quick select is numbered by position, taken keys are skipped when binding it
+            if !is_set(name) && *name != "quick_select" {
+                sequences.retain(|sequence| !taken.contains_key(sequence));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line of the config where `sequence` is bound to the action `name`
+fn binding_line(
+    content: &str,
+    profile: Option<&str>,
+    name: &str,
+    sequence: &KeySequence,
+) -> Option<usize> {
+    let profile = profile.map(|profile| format!("profile.{}.", profile));
+    let mut section = "";
+    let mut key = String::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') {
+            section = line.trim_matches(['[', ']']).trim();
+            continue;
+        }
+        if let Some((k, _)) = line.split_once('=') {
+            // Dotted keys as in `-o keybindings.exit=...` are full paths without a section
+            key = match section {
+                "" => k.trim().to_owned(),
+                _ => format!("{}.{}", section, k.trim()),
+            };
+        }
+        let path = match &profile {
+            Some(profile) => key.strip_prefix(profile.as_str()).unwrap_or(&key),
+            None => &key,
+        };
+        let found = match path.strip_prefix("keybindings.") {
+            Some(custom) if name == "custom" => custom
+                .strip_prefix("custom.")
+                .is_some_and(|keys| parses_to(keys.trim_matches(['"', '\'']), sequence)),
+            Some(action) if action == name => line
+                .split(['"', '\''])
+                .skip(1)
+                .step_by(2)
+                .any(|value| parses_to(value, sequence)),
+            _ => false,
+        };
+        if found {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+fn parses_to(value: &str, sequence: &KeySequence) -> bool {
+    value
+        .parse::<KeySequence>()
+        .is_ok_and(|parsed| parsed == *sequence)
+}
+
+/// Shell command run on the selected result, in place of executing it
+#[derive(Deserialize, Clone, Debug)]
+#[serde(from = "CustomCommandConfig")]
+pub struct CustomCommand {
+    /// `{value}`, `{name}` and `{query}` are replaced by the quoted values
+    pub command: String,
+    /// Close kickoff once the command is started
+    pub close: bool,
+}
+
+/// Either only the command or a table with the command and the close flag
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CustomCommandConfig {
+    Command(String),
+    Table {
+        command: String,
+        close: Option<bool>,
+    },
+}
+
+impl From<CustomCommandConfig> for CustomCommand {
+    fn from(config: CustomCommandConfig) -> Self {
+        match config {
+            CustomCommandConfig::Command(command) => CustomCommand {
+                command,
+                close: true,
+            },
+            CustomCommandConfig::Table { command, close } => CustomCommand {
+                command,
+                close: close.unwrap_or(true),
+            },
+        }
+    }
+}
+
+/// Reads `[keybindings.custom]`, a table from key sequences to commands
+fn custom_bindings<'de, D>(deserializer: D) -> Result<Vec<(KeySequence, CustomCommand)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, CustomCommand>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(keys, command)| Ok((keys.parse().map_err(de::Error::custom)?, command)))
+        .collect()
 }
 
 #[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct HistoryConfig {
+    pub backend: BackendKind,
     pub decrease_interval: u64,
+    /// Instead of the default file in `$XDG_CACHE_HOME/kickoff`
+    pub file: Option<PathBuf>,
+}
+
+/// Where the results are read from, on top of the `--from-*` options
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SourcesConfig {
+    pub path: bool,
+    pub desktop: bool,
+    pub stdin: bool,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AliasConfig {
+    pub enabled: bool,
+    pub file: Option<PathBuf>,
+}
+
+/// Clicks needed on a result to execute it
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClickMode {
+    Click,
+    DoubleClick,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MouseConfig {
+    pub execute: ClickMode,
+    /// Close when clicking or tapping outside of the padded content
+    pub close_on_click_outside: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// How results are arranged in the window
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// A list below the query
+    Vertical,
+    /// On the same line as the query, like dmenu
+    Horizontal,
+    /// Tiles with icons, like an app drawer
+    Grid,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GridConfig {
+    pub columns: usize,
+    /// Width and minimum height of a tile in pixels
+    pub tile_size: u32,
+    pub icon_size: u32,
+    /// Long names are wrapped onto this many lines and cut off after
+    pub label_lines: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    Overlay,
+    Top,
+}
+
+/// Width or height of the window, either absolute or relative to the output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    Pixels(u32),
+    Percent(f32),
+}
+
+/// Space between the window and the edges of the output, like in css either
+/// one value for all sides or `[top, right, bottom, left]`
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum Margin {
+    All(i32),
+    Sides([i32; 4]),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub anchor: Anchor,
+    pub width: Size,
+    pub height: Size,
+    pub margin: Margin,
+    pub layer: Layer,
+    pub exclusive_zone: i32,
+    /// Ask the compositor to blur what is behind the window
+    pub blur: bool,
 }
 
 #[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct Config {
+    pub output: String,
+    pub layout: Layout,
+    pub grid: GridConfig,
     pub prompt: String,
     pub padding: u32,
     pub font: Option<String>,
     pub fonts: Vec<String>,
     pub font_size: f32,
+    pub window: WindowConfig,
     pub colors: ColorConfig,
+    pub style: StyleConfig,
     pub history: HistoryConfig,
+    pub sources: SourcesConfig,
+    pub aliases: AliasConfig,
+    pub mouse: MouseConfig,
     pub keybindings: KeybindingsConfig,
 }
 
+fn key(keysym: u32) -> KeySequence {
+    KeyCombo::new(Modifiers::default(), keysym).into()
+}
+
+fn ctrl(keysym: u32) -> KeySequence {
+    KeyCombo::new(
+        ModifiersState {
+            ctrl: true,
+            ..ModifiersState::default()
+        }
+        .into(),
+        keysym,
+    )
+    .into()
+}
+
+fn shift(keysym: u32) -> KeySequence {
+    KeyCombo::new(
+        ModifiersState {
+            shift: true,
+            ..ModifiersState::default()
+        }
+        .into(),
+        keysym,
+    )
+    .into()
+}
+
+fn alt(keysym: u32) -> KeySequence {
+    KeyCombo::new(
+        ModifiersState {
+            alt: true,
+            ..ModifiersState::default()
+        }
+        .into(),
+        keysym,
+    )
+    .into()
+}
+
+fn ctrl_shift(keysym: u32) -> KeySequence {
+    KeyCombo::new(
+        ModifiersState {
+            ctrl: true,
+            shift: true,
+            ..ModifiersState::default()
+        }
+        .into(),
+        keysym,
+    )
+    .into()
+}
+
 impl Default for KeybindingsConfig {
     fn default() -> Self {
         KeybindingsConfig {
-            delete: vec![
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_BackSpace),
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Delete),
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_KP_Delete),
-            ],
+            mode: EditingMode::Default,
+            chord_timeout: 1000,
+            delete: vec![key(keysyms::XKB_KEY_BackSpace)],
             delete_word: vec![
-                KeyCombo::new(
-                    ModifiersState {
-                        ctrl: true,
-                        ..ModifiersState::default()
-                    }
-                    .into(),
-                    keysyms::XKB_KEY_BackSpace,
-                ),
-                KeyCombo::new(
-                    ModifiersState {
-                        ctrl: true,
-                        ..ModifiersState::default()
-                    }
-                    .into(),
-                    keysyms::XKB_KEY_Delete,
-                ),
-                KeyCombo::new(
-                    ModifiersState {
-                        ctrl: true,
-                        ..ModifiersState::default()
-                    }
-                    .into(),
-                    keysyms::XKB_KEY_KP_Delete,
-                ),
+                ctrl(keysyms::XKB_KEY_BackSpace),
+                ctrl(keysyms::XKB_KEY_Delete),
+                ctrl(keysyms::XKB_KEY_KP_Delete),
             ],
-            execute: vec![
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Return),
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_KP_Enter),
+            delete_forward: vec![
+                key(keysyms::XKB_KEY_Delete),
+                key(keysyms::XKB_KEY_KP_Delete),
             ],
-            paste: vec![KeyCombo::new(
-                ModifiersState {
-                    ctrl: true,
-                    ..ModifiersState::default()
-                }
-                .into(),
-                keysyms::XKB_KEY_v,
-            )],
-            complete: vec![KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Tab)],
-            nav_up: vec![
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Up),
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_KP_Up),
+            kill_to_end: vec![ctrl(keysyms::XKB_KEY_k)],
+            kill_to_start: vec![ctrl(keysyms::XKB_KEY_u)],
+            cursor_left: vec![key(keysyms::XKB_KEY_Left), key(keysyms::XKB_KEY_KP_Left)],
+            cursor_right: vec![key(keysyms::XKB_KEY_Right), key(keysyms::XKB_KEY_KP_Right)],
+            cursor_word_left: vec![ctrl(keysyms::XKB_KEY_Left), ctrl(keysyms::XKB_KEY_KP_Left)],
+            cursor_word_right: vec![
+                ctrl(keysyms::XKB_KEY_Right),
+                ctrl(keysyms::XKB_KEY_KP_Right),
+            ],
+            cursor_home: vec![key(keysyms::XKB_KEY_Home), key(keysyms::XKB_KEY_KP_Home)],
+            cursor_end: vec![key(keysyms::XKB_KEY_End), key(keysyms::XKB_KEY_KP_End)],
+            select_left: vec![
+                shift(keysyms::XKB_KEY_Left),
+                shift(keysyms::XKB_KEY_KP_Left),
+            ],
+            select_right: vec![
+                shift(keysyms::XKB_KEY_Right),
+                shift(keysyms::XKB_KEY_KP_Right),
             ],
-            nav_down: vec![
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Down),
-                KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_KP_Down),
+            select_word_left: vec![
+                ctrl_shift(keysyms::XKB_KEY_Left),
+                ctrl_shift(keysyms::XKB_KEY_KP_Left),
             ],
-            exit: vec![KeyCombo::new(Modifiers::default(), keysyms::XKB_KEY_Escape)],
+            select_word_right: vec![
+                ctrl_shift(keysyms::XKB_KEY_Right),
+                ctrl_shift(keysyms::XKB_KEY_KP_Right),
+            ],
+            select_home: vec![
+                shift(keysyms::XKB_KEY_Home),
+                shift(keysyms::XKB_KEY_KP_Home),
+            ],
+            select_end: vec![shift(keysyms::XKB_KEY_End), shift(keysyms::XKB_KEY_KP_End)],
+            select_all: vec![ctrl(keysyms::XKB_KEY_a)],
+            copy: vec![ctrl(keysyms::XKB_KEY_c)],
+            cut: vec![ctrl(keysyms::XKB_KEY_x)],
+            // shift turns the keysym into upper case
+            copy_element: vec![ctrl_shift(keysyms::XKB_KEY_C)],
+            undo: vec![ctrl(keysyms::XKB_KEY_z)],
+            redo: vec![ctrl_shift(keysyms::XKB_KEY_Z), ctrl(keysyms::XKB_KEY_y)],
+            execute: vec![key(keysyms::XKB_KEY_Return), key(keysyms::XKB_KEY_KP_Enter)],
+            paste: vec![ctrl(keysyms::XKB_KEY_v)],
+            complete: vec![key(keysyms::XKB_KEY_Tab)],
+            nav_up: vec![key(keysyms::XKB_KEY_Up), key(keysyms::XKB_KEY_KP_Up)],
+            nav_down: vec![key(keysyms::XKB_KEY_Down), key(keysyms::XKB_KEY_KP_Down)],
+            nav_left: vec![],
+            nav_right: vec![],
+            page_up: vec![key(keysyms::XKB_KEY_Prior), key(keysyms::XKB_KEY_KP_Prior)],
+            page_down: vec![key(keysyms::XKB_KEY_Next), key(keysyms::XKB_KEY_KP_Next)],
+            first: vec![ctrl(keysyms::XKB_KEY_Home), ctrl(keysyms::XKB_KEY_KP_Home)],
+            last: vec![ctrl(keysyms::XKB_KEY_End), ctrl(keysyms::XKB_KEY_KP_End)],
+            quick_select: (keysyms::XKB_KEY_1..=keysyms::XKB_KEY_9).map(alt).collect(),
+            custom: vec![],
+            exit: vec![key(keysyms::XKB_KEY_Escape)],
         }
     }
 }
@@ -117,45 +650,393 @@ impl Default for ColorConfig {
             text: Color(255, 255, 255, 255),
             text_query: Color(229, 192, 123, 255),
             text_selected: Color(97, 175, 239, 255),
+            query_selection: Color(97, 175, 239, 85),
+            border: Color(97, 175, 239, 255),
+            separator: Color(171, 178, 191, 85),
+            selection_background: Color(0, 0, 0, 0),
+            query_background: Color(0, 0, 0, 0),
+            overlay: Color(0, 0, 0, 0),
+            description: Color(130, 137, 151, 255),
+            hover_background: Color(255, 255, 255, 20),
+            scrollbar: Color(171, 178, 191, 85),
+            counter: Color(130, 137, 151, 255),
+            error: Color(224, 108, 117, 255),
+        }
+    }
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        StyleConfig {
+            border_width: 0,
+            border_radius: 0.,
+            line_height: 1.2,
+            row_spacing: 0,
+            row_padding: 0,
+            selection_radius: 0.,
+            separator_width: 0,
+            query_radius: 0.,
+            background_image: None,
+            background_mode: BackgroundMode::Fill,
+            background_opacity: 1.,
+            description_size: 0.75,
+            description_position: DescriptionPosition::Beside,
+            scrollbar: true,
+            scrollbar_width: 4,
+            counter: false,
+            number_hints: false,
         }
     }
 }
 impl Default for Config {
     fn default() -> Self {
         Config {
+            output: FOCUSED.to_owned(),
+            layout: Layout::Vertical,
+            grid: GridConfig::default(),
             prompt: "".to_owned(),
             padding: 100,
             font: None,
             fonts: vec![],
             font_size: 32.,
+            window: WindowConfig::default(),
             colors: ColorConfig::default(),
+            style: StyleConfig::default(),
             history: HistoryConfig::default(),
+            sources: SourcesConfig::default(),
+            aliases: AliasConfig::default(),
+            mouse: MouseConfig::default(),
             keybindings: KeybindingsConfig::default(),
         }
     }
 }
+impl Default for MouseConfig {
+    fn default() -> Self {
+        MouseConfig {
+            execute: ClickMode::DoubleClick,
+            close_on_click_outside: false,
+        }
+    }
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        GridConfig {
+            columns: 5,
+            tile_size: 160,
+            icon_size: 64,
+            label_lines: 2,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            anchor: Anchor::Center,
+            width: Size::Percent(100.),
+            height: Size::Percent(100.),
+            margin: Margin::All(0),
+            layer: Layer::Overlay,
+            exclusive_zone: 0,
+            blur: false,
+        }
+    }
+}
+
+impl Margin {
+    pub fn sides(&self) -> [i32; 4] {
+        match *self {
+            Margin::All(m) => [m; 4],
+            Margin::Sides(sides) => sides,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SizeVisitor)
+    }
+}
+
+struct SizeVisitor;
+/// Compositors take a size of 0 as stretching between the anchors,
+/// without anchors on both sides they disconnect kickoff
+const ZERO_SIZE: &str = "size can not be 0";
+
+impl<'de> Visitor<'de> for SizeVisitor {
+    type Value = Size;
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a size in pixels or a percentage like '50%'")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match u32::try_from(value) {
+            Ok(0) => Err(de::Error::custom(ZERO_SIZE)),
+            Ok(pixels) => Ok(Size::Pixels(pixels)),
+            Err(_) => Err(de::Error::custom("size can not be negative")),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let value = value.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            match percent.trim().parse::<f32>() {
+                Ok(0.) => Err(de::Error::custom(ZERO_SIZE)),
+                Ok(p) if (0. ..=100.).contains(&p) => Ok(Size::Percent(p)),
+                _ => Err(de::Error::custom(format!("invalid percentage '{}'", value))),
+            }
+        } else {
+            match value.trim_end_matches("px").parse() {
+                Ok(0) => Err(de::Error::custom(ZERO_SIZE)),
+                Ok(pixels) => Ok(Size::Pixels(pixels)),
+                Err(_) => Err(de::Error::custom(format!("invalid size '{}'", value))),
+            }
+        }
+    }
+}
+
 impl Default for HistoryConfig {
     fn default() -> Self {
         HistoryConfig {
+            backend: BackendKind::default(),
             decrease_interval: 48,
+            file: None,
+        }
+    }
+}
+
+const DEFAULT_CONFIG: &str = include_str!("../assets/default_config.toml");
+
+/// One source of configuration, a file or a `-o` option
+struct Source {
+    /// Path of the file or the option as given
+    origin: String,
+    content: String,
+    option: bool,
+}
+
+impl Source {
+    fn file(path: &Path) -> Result<Self, String> {
+        Ok(Source {
+            origin: path.display().to_string(),
+            content: read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+            option: false,
+        })
+    }
+
+    /// `key.path=value` as toml, unquoted values are taken as strings
+    fn option(option: &str) -> Result<Self, String> {
+        let (key, value) = option
+            .split_once('=')
+            .ok_or_else(|| format!("-o {}: expected key.path=value", option))?;
+        // Only the key is trimmed, spaces in string values are often intended
+        let key = key.trim();
+        let content = match toml::from_str::<Table>(&format!("{} = {}", key, value)) {
+            Ok(_) => format!("{} = {}", key, value),
+            Err(_) => format!("{} = {}", key, Value::String(value.to_owned())),
+        };
+        Ok(Source {
+            origin: format!("-o {}", option),
+            content,
+            option: true,
+        })
+    }
+
+    fn table(&self) -> Result<Table, String> {
+        toml::from_str(&self.content).map_err(|e| format!("{}: {}", self.origin, e))
+    }
+
+    /// Checks the values on their own, as errors of the merged config can't tell
+    /// the file and line
+    fn validate(&self) -> Result<(), String> {
+        toml::from_str::<Config>(&self.content)
+            .map(drop)
+            .and_then(|_| toml::from_str::<Profiles>(&self.content).map(drop))
+            .map_err(|e| format!("{}: {}", self.origin, e))
+    }
+
+    /// Where `line` of the content is, for error messages
+    fn location(&self, line: usize) -> String {
+        if self.option {
+            self.origin.clone()
+        } else {
+            format!("{}:{}", self.origin, line)
+        }
+    }
+}
+
+/// The `[profile.<name>]` sections of a source
+#[derive(Deserialize)]
+struct Profiles {
+    #[serde(default)]
+    #[allow(dead_code)]
+    profile: BTreeMap<String, Config>,
+}
+
+/// Merges `layer` into `base`, tables key by key, everything else is replaced
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
         }
     }
 }
 
 impl Config {
-    pub fn load(config_path: Option<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Merges the system wide configs in `$XDG_CONFIG_DIRS`, the user config,
+    /// the given files, the `[profile.<name>]` section of `profile` and finally
+    /// the `key.path=value` options, later ones take precedence
+    pub fn load(
+        files: &[PathBuf],
+        options: &[String],
+        profile: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let xdg_dirs = BaseDirectories::with_prefix("kickoff")?;
-        if let Some(config_file) = config_path {
-            let content = read_to_string(config_file)?;
-            Ok(toml::from_str(&content)?)
-        } else if let Some(config_file) = xdg_dirs.find_config_file("config.toml") {
-            let content = read_to_string(config_file)?;
-            Ok(toml::from_str(&content)?)
-        } else {
+        // Lowest priority first
+        let mut paths: Vec<PathBuf> = xdg_dirs.find_config_files("config.toml").collect();
+        if paths.is_empty() {
             let config_file: PathBuf = xdg_dirs.place_config_file("config.toml")?;
-            let default = include_bytes!("../assets/default_config.toml");
-            write(config_file, default)?;
-            Ok(toml::from_str(&String::from_utf8_lossy(default))?)
+            write(&config_file, DEFAULT_CONFIG)?;
+            paths.push(config_file);
+        }
+        let mut sources = Vec::new();
+        for path in paths.iter().chain(files) {
+            sources.push(Source::file(path)?);
         }
+        for option in options {
+            sources.push(Source::option(option)?);
+        }
+        Config::parse(&sources, profile)
+    }
+
+    /// The default config, used when the configured one can't be loaded
+    pub fn fallback() -> Self {
+        let source = Source {
+            origin: "default config".to_owned(),
+            content: DEFAULT_CONFIG.to_owned(),
+            option: false,
+        };
+        Config::parse(&[source], None).unwrap_or_default()
+    }
+
+    /// Directories of the files read by `load` and of the user themes, to watch for changes
+    pub fn dirs(files: &[PathBuf]) -> Vec<PathBuf> {
+        let mut dirs = BTreeSet::new();
+        if let Ok(xdg_dirs) = BaseDirectories::with_prefix("kickoff") {
+            dirs.extend(
+                xdg_dirs
+                    .find_config_files("config.toml")
+                    .filter_map(|path| path.parent().map(Path::to_path_buf)),
+            );
+            dirs.insert(xdg_dirs.get_config_home());
+            dirs.insert(xdg_dirs.get_config_home().join("themes"));
+        }
+        for file in files {
+            match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dirs.insert(dir.to_path_buf()),
+                _ => dirs.insert(PathBuf::from(".")),
+            };
+        }
+        dirs.into_iter().filter(|dir| dir.is_dir()).collect()
+    }
+
+    fn parse(
+        sources: &[Source],
+        profile: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut table = Table::new();
+        for source in sources {
+            merge(&mut table, source.table()?);
+            source.validate()?;
+        }
+        let profiles = match table.remove("profile") {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err("profile has to be a table of [profile.<name>] sections".into()),
+            None => Table::new(),
+        };
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(Value::Table(values)) => merge(&mut table, values.clone()),
+                Some(_) => return Err(format!("profile.{} has to be a table", name).into()),
+                None if profiles.is_empty() => {
+                    return Err(format!("Unknown profile '{}', none are configured", name).into())
+                }
+                None => {
+                    let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                    return Err(format!(
+                        "Unknown profile '{}', available are: {}",
+                        name,
+                        names.join(", ")
+                    )
+                    .into());
+                }
+            }
+            // Options on the command line still take precedence over the profile
+            for source in sources.iter().filter(|source| source.option) {
+                merge(&mut table, source.table()?);
+            }
+            table.remove("profile");
+        }
+        theme::apply(&mut table)?;
+        let keybindings = match table.get("keybindings") {
+            Some(Value::Table(keybindings)) => Some(keybindings.clone()),
+            _ => None,
+        };
+        let mut config: Config = Value::Table(table).try_into()?;
+        if config.keybindings.mode == EditingMode::Emacs {
+            config.keybindings.emacs(keybindings.as_ref());
+        }
+        config
+            .keybindings
+            .check(keybindings.as_ref(), sources, profile)?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emacs_mode_applies_to_default_config() {
+        let sources = [
+            Source {
+                origin: "default config".to_owned(),
+                content: DEFAULT_CONFIG.to_owned(),
+                option: false,
+            },
+            Source::option("keybindings.mode=emacs").unwrap(),
+        ];
+        let mut config = Config::parse(&sources, None).unwrap();
+        let bound_to = |actions: &[(&'static str, &mut Vec<KeySequence>)], keys| {
+            actions
+                .iter()
+                .filter(|(_, sequences)| sequences.contains(&keys))
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+        };
+        let actions = config.keybindings.actions_mut();
+        assert_eq!(
+            bound_to(&actions, ctrl(keysyms::XKB_KEY_a)),
+            ["cursor_home"]
+        );
+        assert_eq!(bound_to(&actions, ctrl(keysyms::XKB_KEY_e)), ["cursor_end"]);
+        assert_eq!(
+            bound_to(&actions, ctrl(keysyms::XKB_KEY_k)),
+            ["kill_to_end"]
+        );
     }
 }
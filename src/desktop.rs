@@ -0,0 +1,85 @@
+use log::*;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::io;
+use xdg::BaseDirectories;
+
+use crate::selection::Element;
+
+/// Collects the applications from the desktop entries in `$XDG_DATA_DIRS/applications`.
+///
+/// Entries in directories with higher priority shadow those with the same file name,
+/// entries that are hidden or no applications are left out.
+pub fn load() -> io::Result<Vec<Element>> {
+    let xdg_dirs = BaseDirectories::new()?;
+    let mut seen = HashSet::new();
+    let mut res = Vec::new();
+    for path in xdg_dirs.list_data_files("applications") {
+        if path.extension().is_none_or(|ext| ext != "desktop") {
+            continue;
+        }
+        if !seen.insert(path.file_name().map(|name| name.to_owned())) {
+            continue;
+        }
+        match read_to_string(&path) {
+            Ok(content) => res.extend(parse_entry(&content)),
+            Err(e) => debug!("Failed to read {}: {}", path.display(), e),
+        }
+    }
+    Ok(res)
+}
+
+/// Reads the `[Desktop Entry]` group, ignoring translations and actions
+fn parse_entry(content: &str) -> Option<Element> {
+    let mut in_entry = false;
+    let (mut name, mut exec, mut comment, mut generic_name) = (None, None, None, None);
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "Type" if value != "Application" => return None,
+            "NoDisplay" | "Hidden" if value == "true" => return None,
+            "Name" => name = Some(value),
+            "Exec" => exec = Some(value),
+            "Comment" => comment = Some(value),
+            "GenericName" => generic_name = Some(value),
+            _ => {}
+        }
+    }
+
+    Some(Element {
+        name: name?.to_owned(),
+        value: strip_field_codes(exec?),
+        description: comment
+            .or(generic_name)
+            .filter(|description| !description.is_empty())
+            .map(str::to_owned),
+        base_score: 0,
+    })
+}
+
+/// Removes the placeholders for files and urls like `%U`, which kickoff never passes
+fn strip_field_codes(exec: &str) -> String {
+    let mut res = String::with_capacity(exec.len());
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            if let Some('%') = chars.next() {
+                res.push('%');
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    res.split_whitespace().collect::<Vec<_>>().join(" ")
+}
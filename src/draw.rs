@@ -0,0 +1,133 @@
+use crate::color::Color;
+use image::{Pixel, Rgba, RgbaImage};
+
+/// Blends a filled rectangle onto the image, clipped to the image bounds
+pub fn fill_rect(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: &Color) {
+    let x_end = (x + width).min(image.width());
+    let y_end = (y + height).min(image.height());
+    for y in y..y_end {
+        for x in x..x_end {
+            image.get_pixel_mut(x, y).blend(&color.to_rgba());
+        }
+    }
+}
+
+/// Blends a filled rectangle with anti-aliased corners of the given radius onto the image
+pub fn fill_rounded_rect(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    radius: f32,
+    color: &Color,
+) {
+    let outer = RoundedRect::new(x, y, width, height, radius);
+    draw_coverage(image, &outer, color, |px, py| outer.coverage(px, py));
+}
+
+/// Blends the outline of a rounded rectangle onto the image, `thickness` pixels wide on the inside
+#[allow(clippy::too_many_arguments)]
+pub fn stroke_rounded_rect(
+    image: &mut RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    radius: f32,
+    thickness: u32,
+    color: &Color,
+) {
+    if thickness == 0 {
+        return;
+    }
+    let outer = RoundedRect::new(x, y, width, height, radius);
+    let inner = RoundedRect::new(
+        x + thickness,
+        y + thickness,
+        width.saturating_sub(2 * thickness),
+        height.saturating_sub(2 * thickness),
+        (radius - thickness as f32).max(0.),
+    );
+    draw_coverage(image, &outer, color, |px, py| {
+        outer.coverage(px, py) - inner.coverage(px, py)
+    });
+}
+
+/// Makes the corners of the image transparent outside of the given radius.
+/// Only alpha is scaled, colors are premultiplied when the image is handed to the compositor.
+pub fn clip_corners(image: &mut RgbaImage, radius: f32) {
+    let shape = RoundedRect::new(0, 0, image.width(), image.height(), radius);
+    let r = shape.radius.ceil() as u32;
+    let (width, height) = image.dimensions();
+    for y in (0..r.min(height)).chain(height.saturating_sub(r).max(r)..height) {
+        for x in (0..r.min(width)).chain(width.saturating_sub(r).max(r)..width) {
+            let coverage = shape.coverage(x, y);
+            if coverage < 1. {
+                let pixel = image.get_pixel_mut(x, y);
+                pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+            }
+        }
+    }
+}
+
+fn draw_coverage(
+    image: &mut RgbaImage,
+    shape: &RoundedRect,
+    color: &Color,
+    coverage: impl Fn(u32, u32) -> f32,
+) {
+    let x_end = (shape.x + shape.width).ceil().min(image.width() as f32) as u32;
+    let y_end = (shape.y + shape.height).ceil().min(image.height() as f32) as u32;
+    let rgba = color.to_rgba();
+    if rgba[3] == 0 {
+        return;
+    }
+    for y in shape.y as u32..y_end {
+        for x in shape.x as u32..x_end {
+            let coverage = coverage(x, y).clamp(0., 1.);
+            if coverage > 0. {
+                let alpha = (rgba[3] as f32 * coverage).round() as u8;
+                image
+                    .get_pixel_mut(x, y)
+                    .blend(&Rgba([rgba[0], rgba[1], rgba[2], alpha]));
+            }
+        }
+    }
+}
+
+struct RoundedRect {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    radius: f32,
+}
+
+impl RoundedRect {
+    fn new(x: u32, y: u32, width: u32, height: u32, radius: f32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        RoundedRect {
+            x: x as f32,
+            y: y as f32,
+            width,
+            height,
+            radius: radius.clamp(0., width.min(height) / 2.),
+        }
+    }
+
+    /// Portion of the pixel covered by the shape, estimated from the signed distance
+    /// of its center to the edge
+    fn coverage(&self, px: u32, py: u32) -> f32 {
+        if self.width <= 0. || self.height <= 0. {
+            return 0.;
+        }
+        let half_width = self.width / 2.;
+        let half_height = self.height / 2.;
+        let dx = (px as f32 + 0.5 - self.x - half_width).abs() - (half_width - self.radius);
+        let dy = (py as f32 + 0.5 - self.y - half_height).abs() - (half_height - self.radius);
+        let outside = dx.max(0.).hypot(dy.max(0.));
+        let inside = dx.max(dy).min(0.);
+        (0.5 - (outside + inside - self.radius)).clamp(0., 1.)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::color::Color;
 use fontdue::layout::{CoordinateSystem, GlyphRasterConfig, Layout, LayoutSettings, TextStyle};
 use fontdue::Metrics;
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -54,6 +54,22 @@ impl Font {
         })
     }
 
+    /// The same fonts in another size, i.e. for secondary text
+    pub fn with_size(&self, size: f32) -> Font {
+        Font {
+            fonts: self.fonts.clone(),
+            layout: RefCell::new(Layout::new(CoordinateSystem::PositiveYDown)),
+            scale: size,
+            glyph_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Changes the font size, i.e. when the scale of the output changed
+    pub fn set_size(&mut self, size: f32) {
+        self.scale = size;
+        self.glyph_cache.borrow_mut().clear();
+    }
+
     fn render_glyph(&self, conf: GlyphRasterConfig) -> (Metrics, Vec<u8>) {
         let mut glyph_cache = self.glyph_cache.borrow_mut();
         if let Some(bitmap) = glyph_cache.get(&conf) {
@@ -69,15 +85,7 @@ impl Font {
         }
     }
 
-    pub fn render(
-        &mut self,
-        text: &str,
-        color: &Color,
-        image: &mut RgbaImage,
-        x_offset: u32,
-        y_offset: u32,
-    ) -> (u32, u32) {
-        let mut width = 0;
+    fn layout(&self, text: &str) -> RefMut<'_, Layout> {
         let mut layout = self.layout.borrow_mut();
         layout.reset(&LayoutSettings::default());
         for c in text.chars() {
@@ -93,6 +101,46 @@ impl Font {
                 &TextStyle::new(&c.to_string(), self.scale, font_index),
             );
         }
+        layout
+    }
+
+    /// Height of a line of text as intended by the font
+    pub fn line_height(&self) -> u32 {
+        self.fonts
+            .first()
+            .and_then(|font| font.horizontal_line_metrics(self.scale))
+            .map_or(self.scale, |metrics| metrics.new_line_size) as u32
+    }
+
+    /// Distance from the top of a line to the baseline
+    pub fn ascent(&self) -> u32 {
+        self.fonts
+            .first()
+            .and_then(|font| font.horizontal_line_metrics(self.scale))
+            .map_or(self.scale, |metrics| metrics.ascent) as u32
+    }
+
+    /// Horizontal advance of `text`, i.e. where the next character would be placed
+    pub fn measure(&self, text: &str) -> u32 {
+        let layout = self.layout(text);
+        if let Some(glyph) = layout.glyphs().last() {
+            let metrics = self.fonts[glyph.font_index].metrics(glyph.parent, self.scale);
+            (glyph.x - metrics.xmin as f32 + metrics.advance_width).max(0.) as u32
+        } else {
+            0
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        text: &str,
+        color: &Color,
+        image: &mut RgbaImage,
+        x_offset: u32,
+        y_offset: u32,
+    ) -> (u32, u32) {
+        let mut width = 0;
+        let layout = self.layout(text);
 
         for glyph in layout.glyphs() {
             let (_, bitmap) = self.render_glyph(glyph.key);
@@ -101,9 +149,10 @@ impl Font {
                     let x = glyph.x + x_offset as f32 + (i % glyph.width) as f32;
                     let y = glyph.y + y_offset as f32 + (i / glyph.width) as f32;
 
-                    image
-                        .get_pixel_mut(x as u32, y as u32)
-                        .blend(&image::Rgba([color.0, color.1, color.2, *alpha]));
+                    // Text that does not fit into the window is cut off
+                    if let Some(pixel) = image.get_pixel_mut_checked(x as u32, y as u32) {
+                        pixel.blend(&image::Rgba([color.0, color.1, color.2, *alpha]));
+                    }
                 }
             }
         }
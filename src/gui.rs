@@ -1,21 +1,18 @@
 use smithay_client_toolkit::{
     reexports::{
         calloop,
-        client::protocol::{
-            wl_output,
-            wl_pointer::{ButtonState, Event as PEvent},
-            wl_seat, wl_shm, wl_surface,
-        },
+        client::protocol::{wl_compositor, wl_output, wl_seat, wl_shm, wl_surface},
         client::{Attached, DispatchData, Display, Main},
-        protocols::wlr::unstable::layer_shell::v1::client::{
-            zwlr_layer_shell_v1, zwlr_layer_surface_v1,
-            zwlr_layer_surface_v1::KeyboardInteractivity,
+        protocols::{
+            viewporter::client::{wp_viewport, wp_viewporter},
+            wlr::unstable::layer_shell::v1::client::{
+                zwlr_layer_shell_v1, zwlr_layer_surface_v1,
+                zwlr_layer_surface_v1::KeyboardInteractivity,
+            },
         },
     },
     seat::{
-        keyboard::{
-            keysyms, map_keyboard_repeat, Event as KbEvent, KeyState, ModifiersState, RepeatKind,
-        },
+        keyboard::{map_keyboard_repeat, Event as KbEvent, KeyState, ModifiersState, RepeatKind},
         with_seat_data,
     },
     shm::DoubleMemPool,
@@ -25,12 +22,21 @@ use smithay_clipboard::Clipboard;
 
 use log::*;
 use std::cell::Cell;
-use std::io::{BufWriter, ErrorKind, Seek, SeekFrom, Write};
+use std::collections::VecDeque;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::rc::Rc;
+use std::time::Duration;
 
-use image::{Pixel, Rgba, RgbaImage};
+use image::{Rgba, RgbaImage};
 
-use crate::keybinds::Keybindings;
+use crate::config::{Anchor, Layer, Size, WindowConfig};
+use crate::ime::Ime;
+use crate::modes::Modes;
+use crate::pointer::Pointer;
+use crate::protocols::fractional_scale_v1::client::{
+    wp_fractional_scale_manager_v1, wp_fractional_scale_v1,
+};
+use crate::protocols::kde_blur::client::{org_kde_kwin_blur, org_kde_kwin_blur_manager};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub enum RenderEvent {
@@ -38,38 +44,212 @@ pub enum RenderEvent {
     Closed,
 }
 
+/// Globals needed to create a `Surface`, optional ones are not supported by every compositor
+pub struct SurfaceGlobals {
+    pub layer_shell: Attached<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
+    pub viewporter: Option<Attached<wp_viewporter::WpViewporter>>,
+    pub fractional_scale_manager:
+        Option<Attached<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>>,
+    pub compositor: Attached<wl_compositor::WlCompositor>,
+    pub blur_manager: Option<Attached<org_kde_kwin_blur_manager::OrgKdeKwinBlurManager>>,
+}
+
+/// What the compositor may know about the content of the window
+#[derive(Clone, Copy, Debug)]
+pub struct Shape {
+    /// Nothing behind the window shines through, apart from the corners
+    pub opaque: bool,
+    /// Radius of the transparent corners in surface local coordinates
+    pub corner_radius: f32,
+}
+
 pub struct Surface {
     surface: wl_surface::WlSurface,
     layer_surface: Main<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1>,
     pub next_render_event: Rc<Cell<Option<RenderEvent>>>,
     pools: DoubleMemPool,
+    /// Size in surface local coordinates
     pub dimensions: (u32, u32),
+    /// Factor between surface local coordinates and buffer pixels
+    pub scale: f32,
+    /// Integer scale of the outputs the surface is on, set by the compositor
+    output_scale: Rc<Cell<Option<i32>>>,
+    fractional_scale: Option<Main<wp_fractional_scale_v1::WpFractionalScaleV1>>,
+    /// Preferred fractional scale in 120ths, takes precedence over `output_scale`
+    preferred_scale: Rc<Cell<Option<u32>>>,
+    viewport: Option<Main<wp_viewport::WpViewport>>,
+    compositor: Attached<wl_compositor::WlCompositor>,
+    blur: Option<Main<org_kde_kwin_blur::OrgKdeKwinBlur>>,
+    shape: Shape,
+    window: WindowConfig,
+    /// Relative sizes are turned into margins once the size of the output is known
+    needs_resize: bool,
 }
 
 impl Surface {
+    /// Size of the buffer in pixels
+    pub fn physical_dimensions(&self) -> (u32, u32) {
+        (
+            (self.dimensions.0 as f32 * self.scale).ceil() as u32,
+            (self.dimensions.1 as f32 * self.scale).ceil() as u32,
+        )
+    }
+
+    /// Picks up changes of the scale, returns true if the surface needs to be redrawn
+    pub fn update_scale(&mut self) -> bool {
+        let scale = match &self.fractional_scale {
+            Some(_) => self.preferred_scale.take().map(|scale| scale as f32 / 120.),
+            None => self.output_scale.take().map(|scale| scale as f32),
+        };
+        match scale {
+            Some(scale) if scale != self.scale => {
+                self.scale = scale;
+                // With a viewport the buffer size is mapped to the surface size instead
+                if self.viewport.is_none() {
+                    self.surface.set_buffer_scale(scale as i32);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn set_dimensions(&mut self, width: u32, height: u32) -> bool {
+        if self.needs_resize {
+            self.needs_resize = false;
+            self.resize_relative(width, height);
+            return false;
+        }
         if self.dimensions != (width, height) {
             self.dimensions = (width, height);
+            self.update_regions();
             true
         } else {
             false
         }
     }
+
+    /// Changes what the compositor may know about the content, i.e. after the config changed
+    pub fn set_shape(&mut self, shape: Shape) {
+        if self.shape.opaque && !shape.opaque {
+            self.surface.set_opaque_region(None);
+        }
+        self.shape = shape;
+        self.update_regions();
+    }
+
+    /// Tells the compositor which part of the window is opaque or should have the
+    /// background blurred, leaving out the rounded corners. Applied with the next commit.
+    fn update_regions(&self) {
+        if !self.shape.opaque && self.blur.is_none() {
+            return;
+        }
+        let (width, height) = (self.dimensions.0 as i32, self.dimensions.1 as i32);
+        let radius = (self.shape.corner_radius.ceil() as i32)
+            .min(width / 2)
+            .min(height / 2);
+        let region = self.compositor.create_region();
+        region.add(0, radius, width, height - 2 * radius);
+        region.add(radius, 0, width - 2 * radius, height);
+        if self.shape.opaque {
+            self.surface.set_opaque_region(Some(&region));
+        }
+        if let Some(blur) = &self.blur {
+            blur.set_region(Some(&region));
+            blur.commit();
+        }
+        region.destroy();
+    }
+    /// Axes with a relative size are stretched over the whole output at first.
+    /// With the size of the output known from the first configure,
+    /// the surplus is added to the margins to shrink it to the requested size.
+    fn resize_relative(&mut self, width: u32, height: u32) {
+        let [top, right, bottom, left] = self.window.margin.sides();
+        let (anchor_top, anchor_bottom, anchor_left, anchor_right) =
+            anchor_edges(self.window.anchor);
+        let surplus = |size: Size, configured: u32, margins: i32| match size {
+            Size::Percent(p) => {
+                let full = configured as i32 + margins;
+                configured as i32 - (full as f32 * p / 100.) as i32
+            }
+            Size::Pixels(_) => 0,
+        };
+        // Surplus goes to the side the window is not anchored to, or is split when centered
+        let split = |surplus: i32, start: bool, end: bool| match (start, end) {
+            (true, false) => (0, surplus),
+            (false, true) => (surplus, 0),
+            _ => (surplus / 2, surplus - surplus / 2),
+        };
+        let (extra_left, extra_right) = split(
+            surplus(self.window.width, width, left + right),
+            anchor_left,
+            anchor_right,
+        );
+        let (extra_top, extra_bottom) = split(
+            surplus(self.window.height, height, top + bottom),
+            anchor_top,
+            anchor_bottom,
+        );
+        self.layer_surface.set_margin(
+            top + extra_top,
+            right + extra_right,
+            bottom + extra_bottom,
+            left + extra_left,
+        );
+        self.surface.commit();
+    }
+
     pub fn new(
         output: Option<&wl_output::WlOutput>,
         surface: wl_surface::WlSurface,
-        layer_shell: &Attached<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
+        output_scale: Rc<Cell<Option<i32>>>,
         pools: DoubleMemPool,
+        globals: &SurfaceGlobals,
+        window: &WindowConfig,
+        shape: Shape,
     ) -> Self {
-        let layer_surface = layer_shell.get_layer_surface(
-            &surface,
-            output,
-            zwlr_layer_shell_v1::Layer::Overlay,
-            "launcher".to_owned(),
-        );
+        let layer = match window.layer {
+            Layer::Overlay => zwlr_layer_shell_v1::Layer::Overlay,
+            Layer::Top => zwlr_layer_shell_v1::Layer::Top,
+        };
+        let layer_surface =
+            globals
+                .layer_shell
+                .get_layer_surface(&surface, output, layer, "launcher".to_owned());
 
-        // Anchor to all corners of the output to archive fullscreen
-        layer_surface.set_anchor(zwlr_layer_surface_v1::Anchor::all());
+        // Relative sizes are stretched between both edges and shrunk with margins later on
+        let (top, bottom, left, right) = anchor_edges(window.anchor);
+        let mut anchor = zwlr_layer_surface_v1::Anchor::empty();
+        let mut size = (0, 0);
+        match window.width {
+            Size::Pixels(width) => size.0 = width,
+            Size::Percent(_) => {
+                anchor |= zwlr_layer_surface_v1::Anchor::Left | zwlr_layer_surface_v1::Anchor::Right
+            }
+        }
+        match window.height {
+            Size::Pixels(height) => size.1 = height,
+            Size::Percent(_) => {
+                anchor |= zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Bottom
+            }
+        }
+        for (set, edge) in [
+            (top, zwlr_layer_surface_v1::Anchor::Top),
+            (bottom, zwlr_layer_surface_v1::Anchor::Bottom),
+            (left, zwlr_layer_surface_v1::Anchor::Left),
+            (right, zwlr_layer_surface_v1::Anchor::Right),
+        ] {
+            if set {
+                anchor |= edge;
+            }
+        }
+        layer_surface.set_anchor(anchor);
+        layer_surface.set_size(size.0, size.1);
+        let [margin_top, margin_right, margin_bottom, margin_left] = window.margin.sides();
+        layer_surface.set_margin(margin_top, margin_right, margin_bottom, margin_left);
+        layer_surface.set_exclusive_zone(window.exclusive_zone);
+        let needs_resize = matches!(window.width, Size::Percent(p) if p < 100.)
+            || matches!(window.height, Size::Percent(p) if p < 100.);
 
         // Enable Keyboard interactivity
         layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
@@ -96,6 +276,35 @@ impl Surface {
             }
         });
 
+        let viewport = globals
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(&surface));
+
+        // Fractional scaling is only possible by scaling the buffer with a viewport
+        let preferred_scale = Rc::new(Cell::new(None));
+        let fractional_scale = match (&viewport, &globals.fractional_scale_manager) {
+            (Some(_), Some(manager)) => {
+                let preferred_scale_handle = Rc::clone(&preferred_scale);
+                let fractional_scale = manager.get_fractional_scale(&surface);
+                fractional_scale.quick_assign(move |_, event, _| {
+                    let wp_fractional_scale_v1::Event::PreferredScale { scale } = event;
+                    preferred_scale_handle.set(Some(scale));
+                });
+                Some(fractional_scale)
+            }
+            _ => None,
+        };
+
+        let blur = match (&globals.blur_manager, window.blur) {
+            (Some(manager), true) => Some(manager.create(&surface)),
+            (None, true) => {
+                info!("Blur is not supported by the compositor, it might offer a setting for it");
+                None
+            }
+            _ => None,
+        };
+
         // Commit so that the server will send a configure event
         surface.commit();
 
@@ -105,23 +314,37 @@ impl Surface {
             next_render_event,
             pools,
             dimensions: (0, 0),
+            scale: 1.,
+            output_scale,
+            fractional_scale,
+            preferred_scale,
+            viewport,
+            compositor: globals.compositor.clone(),
+            blur,
+            shape,
+            window: window.clone(),
+            needs_resize,
         }
     }
 
+    /// Draws an image of `physical_dimensions`
     pub fn draw(&mut self, mut image: RgbaImage) -> Result<(), std::io::Error> {
+        let (width, height) = self.physical_dimensions();
         if let Some(pool) = self.pools.pool() {
-            let stride = 4 * self.dimensions.0 as i32;
-            let width = self.dimensions.0 as i32;
-            let height = self.dimensions.1 as i32;
+            let stride = 4 * width as i32;
+            let width = width as i32;
+            let height = height as i32;
 
             // First make sure the pool is the right size
             pool.resize((stride * height) as usize)?;
 
             // Create a new buffer from the pool
             let buffer = pool.buffer(0, width, height, stride, wl_shm::Format::Argb8888);
+            // Argb8888 holds premultiplied colors, little endian so in BGRA order
             image.pixels_mut().for_each(|pixel| {
-                let channels = pixel.channels_mut();
-                *pixel = *Rgba::from_slice(&[channels[2], channels[1], channels[0], channels[3]]);
+                let [r, g, b, a] = pixel.0;
+                let premultiply = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+                *pixel = Rgba([premultiply(b), premultiply(g), premultiply(r), a]);
             });
 
             // Write the color to all bytes of the pool
@@ -134,23 +357,46 @@ impl Surface {
 
             // Attach the buffer to the surface and mark the entire surface as damaged
             self.surface.attach(Some(&buffer), 0, 0);
-            self.surface
-                .damage_buffer(0, 0, width as i32, height as i32);
+            self.surface.damage_buffer(0, 0, width, height);
+            if let Some(viewport) = &self.viewport {
+                viewport.set_destination(self.dimensions.0 as i32, self.dimensions.1 as i32);
+            }
 
             // Finally, commit the surface
             self.surface.commit();
             Ok(())
         } else {
-            Err(std::io::Error::new(
-                ErrorKind::Other,
-                "All pools are in use by Wayland",
-            ))
+            Err(std::io::Error::other("All pools are in use by Wayland"))
         }
     }
 }
 
+/// Edges of the output the window is attached to: top, bottom, left, right
+fn anchor_edges(anchor: Anchor) -> (bool, bool, bool, bool) {
+    match anchor {
+        Anchor::Center => (false, false, false, false),
+        Anchor::Top => (true, false, false, false),
+        Anchor::Bottom => (false, true, false, false),
+        Anchor::Left => (false, false, true, false),
+        Anchor::Right => (false, false, false, true),
+        Anchor::TopLeft => (true, false, true, false),
+        Anchor::TopRight => (true, false, false, true),
+        Anchor::BottomLeft => (false, true, true, false),
+        Anchor::BottomRight => (false, true, false, true),
+    }
+}
+
 impl Drop for Surface {
     fn drop(&mut self) {
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+        if let Some(viewport) = &self.viewport {
+            viewport.destroy();
+        }
+        if let Some(blur) = &self.blur {
+            blur.release();
+        }
         self.layer_surface.destroy();
         self.surface.destroy();
     }
@@ -163,30 +409,341 @@ pub enum Action {
     Complete,
     NavUp,
     NavDown,
+    NavLeft,
+    NavRight,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    /// Executes the n-th visible result, counting from 1
+    Select(usize),
+    /// Runs the n-th command from `[keybindings.custom]` on the selection
+    Custom(usize),
+    /// A config file changed
+    Reload,
     Search,
+    Redraw,
     Delete,
     DeleteWord,
+    DeleteForward,
+    KillToEnd,
+    KillToStart,
+    CursorLeft,
+    CursorRight,
+    CursorWordLeft,
+    CursorWordRight,
+    CursorHome,
+    CursorEnd,
+    SelectLeft,
+    SelectRight,
+    SelectWordLeft,
+    SelectWordRight,
+    SelectHome,
+    SelectEnd,
+    SelectAll,
+    Copy,
+    Cut,
+    CopyElement,
+    Undo,
+    Redo,
     Paste,
+    /// Pointer moved to surface local coordinates, or left the surface
+    Hover(Option<(i32, i32)>),
+    /// Left button pressed, `time` in milliseconds tells double clicks apart
+    Click {
+        x: i32,
+        y: i32,
+        time: u32,
+    },
+    /// Touch without moving
+    Tap(i32, i32),
+    /// Wheel steps, positive is down
+    Scroll(i32),
+    /// Vertical movement of a touch in surface local coordinates
+    Drag(i32),
+}
+
+/// State of the query before an edit, used for undo and redo
+#[derive(Clone)]
+struct Snapshot {
+    query: String,
+    cursor: usize,
 }
 
 pub struct DData {
     pub query: String,
-    pub action: Option<Action>,
+    /// Byte offset into `query`, always on a char boundary
+    pub cursor: usize,
+    /// Other end of the selected text in `query`, the selection spans from here to `cursor`
+    pub anchor: Option<usize>,
+    /// Waiting to be handled by the main loop, in order
+    pub actions: VecDeque<Action>,
     pub modifiers: ModifiersState,
     pub clipboard: Clipboard,
-    keybindings: Keybindings,
+    pub ime: Ime,
+    pub pointer: Pointer,
+    keybindings: Modes,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Consecutive typed characters are grouped into a single undo step
+    typing: bool,
 }
 
 impl DData {
-    pub fn new(display: &Display, keybindings: Keybindings) -> DData {
+    pub fn new(display: &Display, keybindings: Modes) -> DData {
         let clipboard = unsafe { Clipboard::new(display.get_display_ptr() as *mut _) };
         DData {
             query: "".to_string(),
-            action: None,
+            cursor: 0,
+            anchor: None,
+            actions: VecDeque::new(),
             modifiers: ModifiersState::default(),
             clipboard,
+            ime: Ime::default(),
+            pointer: Pointer::default(),
             keybindings,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            typing: false,
+        }
+    }
+
+    /// Queues an action for the main loop. Movements of the pointer that happen
+    /// before it gets to them are added up with the last queued one.
+    pub fn push_action(&mut self, action: Action) {
+        match (self.actions.back_mut(), action) {
+            (Some(Action::Scroll(a)), Action::Scroll(b))
+            | (Some(Action::Drag(a)), Action::Drag(b)) => *a += b,
+            (Some(last @ Action::Hover(_)), Action::Hover(_)) => *last = action,
+            (Some(Action::Search), Action::Search) | (Some(Action::Redraw), Action::Redraw) => {}
+            _ => self.actions.push_back(action),
+        }
+    }
+
+    /// Replaces the keybindings, i.e. after the config changed
+    pub fn set_keybindings(&mut self, keybindings: Modes) {
+        self.keybindings = keybindings;
+    }
+
+    /// Editing mode to show in front of the prompt
+    pub fn mode_indicator(&self) -> Option<&'static str> {
+        self.keybindings.indicator()
+    }
+
+    /// Keys of an unfinished key sequence, like `ctrl+x`
+    pub fn pending_keys(&self) -> Option<String> {
+        self.keybindings.pending()
+    }
+
+    /// How long to wait for the next key of a sequence
+    pub fn key_timeout(&self) -> Option<Duration> {
+        self.keybindings.timeout()
+    }
+
+    /// Gives up a key sequence that timed out, running its action if it has one
+    /// and typing its keys otherwise
+    pub fn expire_keys(&mut self) {
+        if let Some(outcome) = self.keybindings.expire() {
+            type_keys(self, &outcome.text);
+            run_actions(self, outcome.actions, false);
+        }
+    }
+
+    /// Byte range of the selected text, if any
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.cursor => {
+                Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+            }
+            _ => None,
+        }
+    }
+
+    fn snapshot(&mut self) {
+        self.undo.push(Snapshot {
+            query: self.query.clone(),
+            cursor: self.cursor,
+        });
+        self.redo.clear();
+        self.typing = false;
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.query = snapshot.query;
+        self.cursor = snapshot.cursor;
+        self.anchor = None;
+        self.typing = false;
+    }
+
+    /// Replaces the whole query, e.g. when completing
+    pub fn set_query(&mut self, text: &str) {
+        self.snapshot();
+        self.query.clear();
+        self.query.push_str(text);
+        self.cursor = self.query.len();
+        self.anchor = None;
+    }
+
+    /// Inserts `text` at the cursor and moves the cursor behind it.
+    /// Selected text is replaced.
+    pub fn insert(&mut self, text: &str) {
+        self.snapshot();
+        self.replace_selection(text);
+    }
+
+    /// Inserts a typed character, grouping it with the preceding ones for undo
+    fn type_text(&mut self, text: &str) {
+        if !self.typing || self.selection().is_some() {
+            self.snapshot();
         }
+        self.replace_selection(text);
+        self.typing = true;
+    }
+
+    fn replace_selection(&mut self, text: &str) {
+        if let Some((start, end)) = self.selection() {
+            self.query.replace_range(start..end, "");
+            self.cursor = start;
+        }
+        self.anchor = None;
+        self.query.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        self.snapshot();
+        self.query.replace_range(start..end, "");
+        self.cursor = start;
+        self.anchor = None;
+    }
+
+    /// Deletes up to `before` bytes in front of and `after` bytes behind the cursor
+    pub fn delete_surrounding(&mut self, before: usize, after: usize) {
+        let mut start = self.cursor.saturating_sub(before);
+        while !self.query.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (self.cursor + after).min(self.query.len());
+        while !self.query.is_char_boundary(end) {
+            end += 1;
+        }
+        self.delete_range(start, end);
+    }
+
+    /// Moves the cursor, extending the selection if `select` is set
+    fn move_cursor(&mut self, to: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = to;
+        self.typing = false;
+    }
+
+    fn prev_char(&self) -> usize {
+        self.query[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_char(&self) -> usize {
+        self.query[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the word in front of the cursor, skipping whitespace
+    fn prev_word(&self) -> usize {
+        let before = self.query[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// End of the word behind the cursor, skipping whitespace
+    fn next_word(&self) -> usize {
+        let after = &self.query[self.cursor..];
+        let skipped = after.len() - after.trim_start().len();
+        after[skipped..]
+            .find(char::is_whitespace)
+            .map_or(self.query.len(), |i| self.cursor + skipped + i)
+    }
+
+    fn copy(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            let text = self.query[start..end].to_owned();
+            self.clipboard.store_primary(text.clone());
+            self.clipboard.store(text);
+        }
+    }
+
+    /// Applies an editing action to the query.
+    /// Returns `false` if the action is not an editing action.
+    fn edit(&mut self, action: Action) -> bool {
+        match (action, self.selection()) {
+            (Action::Delete | Action::DeleteWord | Action::DeleteForward, Some((start, end))) => {
+                self.delete_range(start, end)
+            }
+            (Action::Delete, None) => self.delete_range(self.prev_char(), self.cursor),
+            (Action::DeleteWord, None) => self.delete_range(self.prev_word(), self.cursor),
+            (Action::DeleteForward, None) => {
+                let (start, end) = (self.cursor, self.next_char());
+                self.delete_range(start, end)
+            }
+            (Action::KillToEnd, _) => self.delete_range(self.cursor, self.query.len()),
+            (Action::KillToStart, _) => self.delete_range(0, self.cursor),
+            (Action::CursorLeft, Some((start, _))) => self.move_cursor(start, false),
+            (Action::CursorRight, Some((_, end))) => self.move_cursor(end, false),
+            (Action::CursorLeft, None) => self.move_cursor(self.prev_char(), false),
+            (Action::CursorRight, None) => self.move_cursor(self.next_char(), false),
+            (Action::CursorWordLeft, _) => self.move_cursor(self.prev_word(), false),
+            (Action::CursorWordRight, _) => self.move_cursor(self.next_word(), false),
+            (Action::CursorHome, _) => self.move_cursor(0, false),
+            (Action::CursorEnd, _) => self.move_cursor(self.query.len(), false),
+            (Action::SelectLeft, _) => self.move_cursor(self.prev_char(), true),
+            (Action::SelectRight, _) => self.move_cursor(self.next_char(), true),
+            (Action::SelectWordLeft, _) => self.move_cursor(self.prev_word(), true),
+            (Action::SelectWordRight, _) => self.move_cursor(self.next_word(), true),
+            (Action::SelectHome, _) => self.move_cursor(0, true),
+            (Action::SelectEnd, _) => self.move_cursor(self.query.len(), true),
+            (Action::SelectAll, _) => {
+                self.cursor = self.query.len();
+                self.anchor = Some(0);
+            }
+            (Action::Copy, _) => self.copy(),
+            (Action::Cut, Some((start, end))) => {
+                self.copy();
+                self.delete_range(start, end);
+            }
+            (Action::Undo, _) => {
+                if let Some(snapshot) = self.undo.pop() {
+                    self.redo.push(Snapshot {
+                        query: self.query.clone(),
+                        cursor: self.cursor,
+                    });
+                    self.restore(snapshot);
+                }
+            }
+            (Action::Redo, _) => {
+                if let Some(snapshot) = self.redo.pop() {
+                    self.undo.push(Snapshot {
+                        query: self.query.clone(),
+                        cursor: self.cursor,
+                    });
+                    self.restore(snapshot);
+                }
+            }
+            _ => return false,
+        }
+        true
     }
 }
 
@@ -194,19 +751,7 @@ pub fn register_inputs(
     seats: &[Attached<wl_seat::WlSeat>],
     event_loop: &calloop::EventLoop<DData>,
 ) {
-    for seat in seats {
-        if let Some((has_ptr, _name)) = with_seat_data(seat, |seat_data| {
-            (
-                seat_data.has_pointer && !seat_data.defunct,
-                seat_data.name.clone(),
-            )
-        }) {
-            if has_ptr {
-                let pointer = seat.get_pointer();
-                pointer.quick_assign(move |_, event, ddata| process_pointer_event(event, ddata));
-            }
-        }
-    }
+    crate::pointer::register(seats);
 
     for seat in seats {
         if let Some((has_kbd, name)) = with_seat_data(seat, |seat_data| {
@@ -230,105 +775,79 @@ pub fn register_inputs(
     }
 }
 
-fn process_pointer_event(event: PEvent, mut data: DispatchData) {
-    let DData {
-        query,
-        action,
-        clipboard,
-        ..
-    } = data.get::<DData>().unwrap();
-    if let PEvent::Button { button, state, .. } = event {
-        if button == 274 && state == ButtonState::Pressed {
-            if let Ok(txt) = clipboard.load_primary() {
-                query.push_str(&txt);
-                *action = Some(Action::Search);
-            }
-        }
-    }
-}
-
 fn process_keyboard_event(event: KbEvent, mut data: DispatchData) {
-    let DData {
-        query,
-        action,
-        modifiers,
-        clipboard,
-        keybindings,
-        ..
-    } = data.get::<DData>().unwrap();
+    let data = data.get::<DData>().unwrap();
     match event {
         KbEvent::Enter { .. } => {}
         KbEvent::Leave { .. } => {
-            *action = Some(Action::Exit);
+            data.push_action(Action::Exit);
         }
         KbEvent::Key {
+            rawkey,
             keysym,
             state,
             utf8,
             ..
         } => {
             if state == KeyState::Pressed {
-                if let Some(a) = keybindings.get(modifiers, keysym) {
-                    match a {
-                        &Action::Delete => {
-                            query.pop();
-                            *action = Some(Action::Search)
-                        }
-                        &Action::DeleteWord => {
-                            query.pop();
-                            loop {
-                                let removed_char = query.pop();
-                                if removed_char.unwrap_or(' ') == ' ' {
-                                    break;
-                                }
-                            }
-                            *action = Some(Action::Search)
-                        }
-                        &Action::Paste => {
-                            if let (KeyState::Pressed, keysyms::XKB_KEY_v, Ok(txt)) =
-                                (state, keysym, clipboard.load())
-                            {
-                                query.push_str(&txt);
-                                *action = Some(Action::Search);
-                            }
-                        }
-                        a => *action = Some(a.to_owned()),
-                    }
-                } else if let Some(txt) = utf8 {
-                    let t_sanitized = txt
-                        .chars()
-                        .filter(|c| c.is_ascii() && !c.is_ascii_control())
-                        .collect::<String>();
-
-                    query.push_str(&t_sanitized);
-                    *action = Some(Action::Search);
-                }
+                process_key(data, keysym, rawkey, utf8, false);
             }
         }
-        KbEvent::Modifiers { modifiers: m } => *modifiers = m,
-        KbEvent::Repeat { keysym, utf8, .. } => {
-            if let Some(a) = keybindings.get(modifiers, keysym) {
-                match a {
-                    &Action::Delete => {
-                        query.pop();
-                        *action = Some(Action::Search)
-                    }
-                    &Action::DeleteWord => {
-                        query.pop();
-                        loop {
-                            let removed_char = query.pop();
-                            if removed_char.unwrap_or(' ') == ' ' {
-                                break;
-                            }
-                        }
-                        *action = Some(Action::Search)
-                    }
-                    a => *action = Some(a.to_owned()),
+        KbEvent::Modifiers { modifiers: m } => data.modifiers = m,
+        KbEvent::Repeat {
+            rawkey,
+            keysym,
+            utf8,
+            ..
+        } => process_key(data, keysym, rawkey, utf8, true),
+    }
+}
+
+fn run_actions(data: &mut DData, actions: Vec<Action>, repeat: bool) {
+    let query = data.query.clone();
+    for a in actions {
+        match a {
+            Action::Cut if data.selection().is_none() => {}
+            Action::Paste => {
+                if let (false, Ok(txt)) = (repeat, data.clipboard.load()) {
+                    data.insert(&txt);
                 }
-            } else if let Some(txt) = utf8 {
-                query.push_str(&txt);
-                *action = Some(Action::Search);
+            }
+            a => {
+                data.edit(a);
+                data.push_action(a);
             }
         }
     }
+    // Only moving the cursor does not require a new search
+    if data.query != query {
+        data.push_action(Action::Search);
+    }
+}
+
+/// `rawkey` is the evdev keycode of the key, xkb keycodes are offset by 8
+fn process_key(data: &mut DData, keysym: u32, rawkey: u32, utf8: Option<String>, repeat: bool) {
+    let outcome = data
+        .keybindings
+        .get(&data.modifiers, keysym, rawkey + 8, utf8.as_deref());
+    if let Some(outcome) = outcome {
+        type_keys(data, &outcome.text);
+        run_actions(data, outcome.actions, repeat);
+    } else if let Some(txt) = utf8 {
+        type_keys(data, &txt);
+    }
+}
+
+fn type_keys(data: &mut DData, txt: &str) {
+    let t_sanitized = txt
+        .chars()
+        .filter(|c| c.is_ascii() && !c.is_ascii_control())
+        .collect::<String>();
+    // Unbound shortcuts arrive as control characters, which must not replace the selection
+    if t_sanitized.is_empty() {
+        return;
+    }
+
+    data.type_text(&t_sanitized);
+    data.push_action(Action::Search);
 }
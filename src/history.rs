@@ -1,24 +1,124 @@
 extern crate xdg;
 
-use log::*;
 use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
 use std::path::PathBuf;
 use std::time::SystemTime;
 use xdg::BaseDirectories;
 
 use crate::selection::Element;
 
-#[derive(Debug, Serialize, Deserialize)]
+mod csv;
+mod sqlite;
+
+pub use self::csv::CsvBackend;
+pub use self::sqlite::SqliteBackend;
+
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(std::io::Error),
+    Csv(::csv::Error),
+    Sqlite(rusqlite::Error),
+    Xdg(xdg::BaseDirectoriesError),
+    /// Database was written by a newer version of kickoff
+    UnsupportedVersion(usize),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Io(e) => write!(f, "{}", e),
+            HistoryError::Csv(e) => write!(f, "{}", e),
+            HistoryError::Sqlite(e) => write!(f, "{}", e),
+            HistoryError::Xdg(e) => write!(f, "{}", e),
+            HistoryError::UnsupportedVersion(v) => write!(
+                f,
+                "History database has schema version {}, which is newer than this version of kickoff supports",
+                v
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl From<std::io::Error> for HistoryError {
+    fn from(e: std::io::Error) -> Self {
+        HistoryError::Io(e)
+    }
+}
+
+impl From<::csv::Error> for HistoryError {
+    fn from(e: ::csv::Error) -> Self {
+        HistoryError::Csv(e)
+    }
+}
+
+impl From<rusqlite::Error> for HistoryError {
+    fn from(e: rusqlite::Error) -> Self {
+        HistoryError::Sqlite(e)
+    }
+}
+
+impl From<xdg::BaseDirectoriesError> for HistoryError {
+    fn from(e: xdg::BaseDirectoriesError) -> Self {
+        HistoryError::Xdg(e)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HistoryEntry {
     pub name: String,
     pub value: String,
     pub num_used: usize,
 }
 
+/// A single launch, recorded alongside the query that led to it.
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub name: String,
+    pub query: String,
+    pub timestamp: u64,
+}
+
+/// Storage for history entries.
+///
+/// `load` is called once on startup and has to apply the decrease interval itself,
+/// since only the backend knows when an entry was last touched.
+pub trait HistoryBackend: Debug + Send {
+    fn load(&mut self, decrease_interval: u64) -> Result<Vec<HistoryEntry>, HistoryError>;
+    fn save(&mut self, entries: &[HistoryEntry], launches: &[Launch]) -> Result<(), HistoryError>;
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Csv,
+    Sqlite,
+}
+
+impl BackendKind {
+    fn default_file_name(&self) -> &'static str {
+        match self {
+            BackendKind::Csv => "default.csv",
+            BackendKind::Sqlite => "default.sqlite",
+        }
+    }
+
+    fn open(&self, path: PathBuf) -> Result<Box<dyn HistoryBackend>, HistoryError> {
+        Ok(match self {
+            BackendKind::Csv => Box::new(CsvBackend::new(path)),
+            BackendKind::Sqlite => Box::new(SqliteBackend::open(path)?),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct History {
     entries: Vec<HistoryEntry>,
-    path: PathBuf,
+    launches: Vec<Launch>,
+    backend: Box<dyn HistoryBackend>,
 }
 
 impl History {
@@ -26,59 +126,30 @@ impl History {
         &self.entries
     }
 
-    pub fn load(path: Option<PathBuf>, decrease_interval: u64) -> Result<Self, std::io::Error> {
+    pub fn load(
+        path: Option<PathBuf>,
+        kind: BackendKind,
+        decrease_interval: u64,
+    ) -> Result<Self, HistoryError> {
         let history_path = if let Some(path) = path {
             path
         } else {
             let xdg_dirs = BaseDirectories::with_prefix("kickoff")?;
-            if let Some(path) = xdg_dirs.find_cache_file("default.csv") {
-                path
-            } else {
-                return Ok(History {
-                    entries: Vec::new(),
-                    path: xdg_dirs.place_cache_file("default.csv")?,
-                });
+            match xdg_dirs.find_cache_file(kind.default_file_name()) {
+                Some(path) => path,
+                None => xdg_dirs.place_cache_file(kind.default_file_name())?,
             }
         };
 
-        let mut res = History {
-            entries: Vec::new(),
-            path: history_path.clone(),
-        };
-
-        if history_path.exists() {
-            let last_modified = history_path.metadata()?.modified()?;
-            let interval_diff = if decrease_interval > 0 {
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs()
-                    / (3600 * decrease_interval)
-                    - last_modified
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
-                        / (3600 * decrease_interval)
-            } else {
-                0
-            };
-
-            let mut rdr = csv::Reader::from_path(history_path).unwrap();
-            for result in rdr.deserialize() {
-                let mut record: HistoryEntry = result?;
-                record.num_used = record.num_used.saturating_sub(interval_diff as usize);
-                if record.num_used > 0 {
-                    res.entries.push(record);
-                }
-            }
-        } else {
-            info!("History file does not exists, will be created on saving");
-        }
-
-        Ok(res)
+        let mut backend = kind.open(history_path)?;
+        Ok(History {
+            entries: backend.load(decrease_interval)?,
+            launches: Vec::new(),
+            backend,
+        })
     }
 
-    pub fn inc(&mut self, element: &Element) {
+    pub fn inc(&mut self, element: &Element, query: &str) {
         if let Some(entry) = self.entries.iter_mut().find(|x| x.name == element.name) {
             entry.num_used += 1;
             entry.value = element.value.to_owned();
@@ -89,15 +160,33 @@ impl History {
                 num_used: 1,
             })
         }
+        self.launches.push(Launch {
+            name: element.name.to_owned(),
+            query: query.to_owned(),
+            timestamp: now(),
+        });
     }
 
-    pub fn save(&self) -> Result<(), std::io::Error> {
-        let mut wtr = csv::Writer::from_path(&self.path)?;
-        for entry in &self.entries {
-            wtr.serialize(entry)?;
-        }
-        wtr.flush()?;
-
+    pub fn save(&mut self) -> Result<(), HistoryError> {
+        self.backend.save(&self.entries, &self.launches)?;
+        self.launches.clear();
         Ok(())
     }
 }
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Number of whole decrease intervals that passed between `since` and now.
+fn intervals_since(since: u64, decrease_interval: u64) -> usize {
+    if decrease_interval > 0 {
+        (now() / (3600 * decrease_interval)).saturating_sub(since / (3600 * decrease_interval))
+            as usize
+    } else {
+        0
+    }
+}
@@ -0,0 +1,60 @@
+use log::*;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use super::{intervals_since, HistoryBackend, HistoryEntry, HistoryError, Launch};
+
+/// Plain csv file holding name, value and usage count.
+///
+/// The whole file is rewritten on every save and the modification time of
+/// the file is used to decrease all entries at once.
+#[derive(Debug)]
+pub struct CsvBackend {
+    path: PathBuf,
+}
+
+impl CsvBackend {
+    pub fn new(path: PathBuf) -> Self {
+        CsvBackend { path }
+    }
+}
+
+impl HistoryBackend for CsvBackend {
+    fn load(&mut self, decrease_interval: u64) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut res = Vec::new();
+
+        if self.path.exists() {
+            let last_modified = self
+                .path
+                .metadata()?
+                .modified()?
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let interval_diff = intervals_since(last_modified, decrease_interval);
+
+            let mut rdr = csv::Reader::from_path(&self.path)?;
+            for result in rdr.deserialize() {
+                let mut record: HistoryEntry = result?;
+                record.num_used = record.num_used.saturating_sub(interval_diff);
+                if record.num_used > 0 {
+                    res.push(record);
+                }
+            }
+        } else {
+            info!("History file does not exists, will be created on saving");
+        }
+
+        Ok(res)
+    }
+
+    fn save(&mut self, entries: &[HistoryEntry], _launches: &[Launch]) -> Result<(), HistoryError> {
+        let mut wtr = csv::Writer::from_path(&self.path)?;
+        for entry in entries {
+            wtr.serialize(entry)?;
+        }
+        wtr.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+
+use super::{intervals_since, HistoryBackend, HistoryEntry, HistoryError, Launch};
+
+/// Schema migrations, applied in order. The index of the last applied
+/// migration + 1 is kept in `PRAGMA user_version`.
+/// Never edit an existing entry, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE entries (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        value TEXT NOT NULL,
+        num_used INTEGER NOT NULL,
+        last_used INTEGER NOT NULL
+    );
+    CREATE TABLE launches (
+        id INTEGER PRIMARY KEY,
+        entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+        query TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX launches_entry ON launches(entry_id);
+    CREATE TABLE queries (
+        query TEXT NOT NULL,
+        entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+        num_used INTEGER NOT NULL,
+        PRIMARY KEY (query, entry_id)
+    );",
+];
+
+/// Embedded sqlite database.
+///
+/// Unlike the csv backend, only launched entries are written on save and
+/// every entry is decreased based on its own last use.
+#[derive(Debug)]
+pub struct SqliteBackend {
+    conn: Connection,
+}
+
+impl SqliteBackend {
+    pub fn open(path: PathBuf) -> Result<Self, HistoryError> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(SqliteBackend { conn })
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), HistoryError> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(HistoryError::UnsupportedVersion(version));
+    }
+
+    let tx = conn.transaction()?;
+    for migration in &MIGRATIONS[version..] {
+        tx.execute_batch(migration)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+impl HistoryBackend for SqliteBackend {
+    fn load(&mut self, decrease_interval: u64) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, value, num_used, last_used FROM entries")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                HistoryEntry {
+                    name: row.get(0)?,
+                    value: row.get(1)?,
+                    num_used: row.get(2)?,
+                },
+                row.get::<_, u64>(3)?,
+            ))
+        })?;
+
+        let mut res = Vec::new();
+        for row in rows {
+            let (mut entry, last_used) = row?;
+            entry.num_used = entry
+                .num_used
+                .saturating_sub(intervals_since(last_used, decrease_interval));
+            if entry.num_used > 0 {
+                res.push(entry);
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn save(&mut self, entries: &[HistoryEntry], launches: &[Launch]) -> Result<(), HistoryError> {
+        let tx = self.conn.transaction()?;
+        for launch in launches {
+            let entry = match entries.iter().find(|e| e.name == launch.name) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let entry_id: i64 = tx.query_row(
+                "INSERT INTO entries (name, value, num_used, last_used) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(name) DO UPDATE SET
+                    value = excluded.value,
+                    num_used = excluded.num_used,
+                    last_used = excluded.last_used
+                 RETURNING id",
+                params![entry.name, entry.value, entry.num_used, launch.timestamp],
+                |row| row.get(0),
+            )?;
+            tx.execute(
+                "INSERT INTO launches (entry_id, query, timestamp) VALUES (?1, ?2, ?3)",
+                params![entry_id, launch.query, launch.timestamp],
+            )?;
+            if !launch.query.is_empty() {
+                tx.execute(
+                    "INSERT INTO queries (query, entry_id, num_used) VALUES (?1, ?2, 1)
+                     ON CONFLICT(query, entry_id) DO UPDATE SET num_used = num_used + 1",
+                    params![launch.query, entry_id],
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use image::{imageops, RgbaImage};
+use log::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use xdg::BaseDirectories;
+
+/// Sizes of the hicolor theme, searched from small to large
+const SIZES: [u32; 10] = [16, 22, 24, 32, 48, 64, 96, 128, 256, 512];
+const PIXMAPS: &str = "/usr/share/pixmaps";
+
+/// Application icons from the hicolor theme, loaded lazily and kept scaled to one size.
+/// Only PNG icons are supported.
+pub struct Icons {
+    size: u32,
+    dirs: Option<BaseDirectories>,
+    cache: HashMap<String, Option<RgbaImage>>,
+}
+
+impl Icons {
+    pub fn new() -> Self {
+        Icons {
+            size: 0,
+            dirs: BaseDirectories::new().ok(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the icon for `name` scaled to `size`x`size` pixels, if one is installed
+    pub fn get(&mut self, name: &str, size: u32) -> Option<&RgbaImage> {
+        if size != self.size {
+            self.size = size;
+            self.cache.clear();
+        }
+        if !self.cache.contains_key(name) {
+            let icon = self.find(name).and_then(|path| load(&path, size));
+            self.cache.insert(name.to_owned(), icon);
+        }
+        self.cache.get(name).and_then(Option::as_ref)
+    }
+
+    /// Prefers the smallest icon that is at least as large as needed, to keep scaling cheap
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        let mut fallback = None;
+        if let Some(dirs) = &self.dirs {
+            for size in SIZES {
+                let file = format!("icons/hicolor/{size}x{size}/apps/{name}.png");
+                if let Some(path) = dirs.find_data_file(file) {
+                    if size >= self.size {
+                        return Some(path);
+                    }
+                    fallback = Some(path);
+                }
+            }
+        }
+        fallback.or_else(|| {
+            let path = Path::new(PIXMAPS).join(format!("{}.png", name));
+            path.is_file().then_some(path)
+        })
+    }
+}
+
+fn load(path: &Path, size: u32) -> Option<RgbaImage> {
+    match image::open(path) {
+        Ok(image) => Some(imageops::resize(
+            &image.into_rgba8(),
+            size,
+            size,
+            imageops::FilterType::Triangle,
+        )),
+        Err(e) => {
+            warn!("Failed to load icon {}: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use smithay_client_toolkit::reexports::{
+    client::{protocol::wl_seat, Attached, DispatchData, Main},
+    protocols::unstable::text_input::v3::client::{
+        zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+        zwp_text_input_v3::{ContentHint, ContentPurpose, Event as TiEvent, ZwpTextInputV3},
+    },
+};
+
+use crate::gui::{Action, DData};
+
+/// Text being composed by an input method, not yet part of the query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// Byte range inside `text` to draw the cursor at, `None` if it should be hidden
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// Changes sent by the input method, applied together on `done`
+#[derive(Default)]
+struct Pending {
+    preedit: Option<Preedit>,
+    commit: Option<String>,
+    delete: (usize, usize),
+}
+
+/// Cursor rectangle in surface local coordinates: x, y, width, height
+pub type Rect = (i32, i32, i32, i32);
+
+#[derive(PartialEq, Eq)]
+struct Surrounding {
+    text: String,
+    cursor: usize,
+    anchor: usize,
+    rect: Rect,
+}
+
+/// State of the text-input-v3 protocol for all seats
+#[derive(Default)]
+pub struct Ime {
+    active: Option<Main<ZwpTextInputV3>>,
+    pending: Pending,
+    pub preedit: Option<Preedit>,
+    /// Last state sent to the compositor, to avoid needless commits
+    sent: Option<Surrounding>,
+}
+
+impl Ime {
+    /// Tells the input method about the current query and where the cursor is drawn,
+    /// so the candidate window can be placed next to it.
+    pub fn update(&mut self, query: &str, cursor: usize, anchor: usize, rect: Rect) {
+        if let Some(text_input) = &self.active {
+            let state = Surrounding {
+                text: query.to_owned(),
+                cursor,
+                anchor,
+                rect,
+            };
+            if self.sent.as_ref() != Some(&state) {
+                text_input.set_surrounding_text(query.to_owned(), cursor as i32, anchor as i32);
+                text_input.set_cursor_rectangle(rect.0, rect.1, rect.2, rect.3);
+                text_input.commit();
+                self.sent = Some(state);
+            }
+        }
+    }
+}
+
+pub fn register(seats: &[Attached<wl_seat::WlSeat>], manager: &Attached<ZwpTextInputManagerV3>) {
+    for seat in seats {
+        let text_input = manager.get_text_input(seat);
+        text_input.quick_assign(|text_input, event, ddata| {
+            process_text_input_event(text_input, event, ddata)
+        });
+    }
+}
+
+fn process_text_input_event(
+    text_input: Main<ZwpTextInputV3>,
+    event: TiEvent,
+    mut data: DispatchData,
+) {
+    let data = data.get::<DData>().unwrap();
+    match event {
+        TiEvent::Enter { .. } => {
+            text_input.enable();
+            text_input.set_content_type(ContentHint::None, ContentPurpose::Normal);
+            text_input.commit();
+            data.ime.active = Some(text_input);
+            data.ime.sent = None;
+            data.push_action(Action::Redraw);
+        }
+        TiEvent::Leave { .. } => {
+            text_input.disable();
+            text_input.commit();
+            data.ime.active = None;
+            data.ime.preedit = None;
+            data.push_action(Action::Redraw);
+        }
+        TiEvent::PreeditString {
+            text,
+            cursor_begin,
+            cursor_end,
+        } => {
+            data.ime.pending.preedit = text.map(|text| Preedit {
+                cursor: if cursor_begin < 0 || cursor_end < 0 {
+                    None
+                } else {
+                    Some((
+                        (cursor_begin as usize).min(text.len()),
+                        (cursor_end as usize).min(text.len()),
+                    ))
+                },
+                text,
+            });
+        }
+        TiEvent::CommitString { text } => data.ime.pending.commit = text,
+        TiEvent::DeleteSurroundingText {
+            before_length,
+            after_length,
+        } => data.ime.pending.delete = (before_length as usize, after_length as usize),
+        TiEvent::Done { .. } => {
+            let pending = std::mem::take(&mut data.ime.pending);
+            let query = data.query.clone();
+
+            data.ime.preedit = None;
+            if pending.delete != (0, 0) {
+                data.delete_surrounding(pending.delete.0, pending.delete.1);
+            }
+            if let Some(text) = pending.commit {
+                data.insert(&text);
+            }
+            data.ime.preedit = pending.preedit.filter(|preedit| !preedit.text.is_empty());
+
+            data.push_action(if data.query != query {
+                Action::Search
+            } else {
+                Action::Redraw
+            });
+        }
+        _ => {}
+    }
+}
@@ -1,32 +1,88 @@
 use crate::gui::Action;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer};
-use smithay_client_toolkit::seat::keyboard::ModifiersState;
+use smithay_client_toolkit::seat::keyboard::{keysyms, ModifiersState};
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-use x11_keysymdef::lookup_by_name;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use x11_keysymdef::{lookup_by_keysym, lookup_by_name};
 
 use crate::config::KeybindingsConfig;
 
+/// Prefix tree of key sequences, a node can have an action and continue at the same time
+#[derive(Default)]
+struct Node {
+    action: Option<Action>,
+    next: HashMap<KeyCombo, Node>,
+}
+
 pub struct Keybindings {
-    inner: HashMap<KeyCombo, Action>,
+    root: Node,
+    /// Keys of a sequence typed so far
+    pending: Vec<KeyCombo>,
+    /// Text of the pending keys, typed if the sequence turns out to have no action
+    text: String,
+    deadline: Option<Instant>,
+    timeout: Duration,
 }
 
 impl From<KeybindingsConfig> for Keybindings {
     fn from(config: KeybindingsConfig) -> Self {
         let mut res = Keybindings {
-            inner: HashMap::new(),
+            root: Node::default(),
+            pending: Vec::new(),
+            text: String::new(),
+            deadline: None,
+            timeout: Duration::from_millis(config.chord_timeout),
         };
 
-        res.add_key_combos(Action::Complete, &config.complete);
-        res.add_key_combos(Action::Execute, &config.execute);
-        res.add_key_combos(Action::Exit, &config.exit);
-        res.add_key_combos(Action::Delete, &config.delete);
-        res.add_key_combos(Action::DeleteWord, &config.delete_word);
-        res.add_key_combos(Action::NavUp, &config.nav_up);
-        res.add_key_combos(Action::NavDown, &config.nav_down);
-        res.add_key_combos(Action::Paste, &config.paste);
+        res.add_sequences(Action::Complete, &config.complete);
+        res.add_sequences(Action::Execute, &config.execute);
+        res.add_sequences(Action::Exit, &config.exit);
+        res.add_sequences(Action::Delete, &config.delete);
+        res.add_sequences(Action::DeleteWord, &config.delete_word);
+        res.add_sequences(Action::DeleteForward, &config.delete_forward);
+        res.add_sequences(Action::KillToEnd, &config.kill_to_end);
+        res.add_sequences(Action::KillToStart, &config.kill_to_start);
+        res.add_sequences(Action::CursorLeft, &config.cursor_left);
+        res.add_sequences(Action::CursorRight, &config.cursor_right);
+        res.add_sequences(Action::CursorWordLeft, &config.cursor_word_left);
+        res.add_sequences(Action::CursorWordRight, &config.cursor_word_right);
+        res.add_sequences(Action::CursorHome, &config.cursor_home);
+        res.add_sequences(Action::CursorEnd, &config.cursor_end);
+        res.add_sequences(Action::SelectLeft, &config.select_left);
+        res.add_sequences(Action::SelectRight, &config.select_right);
+        res.add_sequences(Action::SelectWordLeft, &config.select_word_left);
+        res.add_sequences(Action::SelectWordRight, &config.select_word_right);
+        res.add_sequences(Action::SelectHome, &config.select_home);
+        res.add_sequences(Action::SelectEnd, &config.select_end);
+        res.add_sequences(Action::SelectAll, &config.select_all);
+        res.add_sequences(Action::Copy, &config.copy);
+        res.add_sequences(Action::Cut, &config.cut);
+        res.add_sequences(Action::CopyElement, &config.copy_element);
+        res.add_sequences(Action::Undo, &config.undo);
+        res.add_sequences(Action::Redo, &config.redo);
+        res.add_sequences(Action::NavUp, &config.nav_up);
+        res.add_sequences(Action::NavDown, &config.nav_down);
+        res.add_sequences(Action::NavLeft, &config.nav_left);
+        res.add_sequences(Action::NavRight, &config.nav_right);
+        res.add_sequences(Action::PageUp, &config.page_up);
+        res.add_sequences(Action::PageDown, &config.page_down);
+        res.add_sequences(Action::First, &config.first);
+        res.add_sequences(Action::Last, &config.last);
+        res.add_sequences(Action::Paste, &config.paste);
+        for (i, (sequence, _)) in config.custom.iter().enumerate() {
+            res.add_sequences(Action::Custom(i), std::slice::from_ref(sequence));
+        }
+        // Last, so a default key taken by another action leaves the other numbers alone
+        for (i, sequence) in config.quick_select.iter().enumerate() {
+            if !res.is_bound(sequence) {
+                res.add_sequences(Action::Select(i + 1), std::slice::from_ref(sequence));
+            }
+        }
 
         res
     }
@@ -63,70 +119,524 @@ impl Eq for Modifiers {}
 #[derive(Eq, PartialEq, Hash, Clone, fmt::Debug)]
 pub struct KeyCombo {
     modifiers: Modifiers,
-    key: u32,
+    key: Key,
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, fmt::Debug)]
+enum Key {
+    /// Symbol of the key in the current layout
+    Sym(u32),
+    /// Physical key, as xkb keycode like shown by `xev` or `wev`
+    Code(u32),
+}
+
+/// Keys of a sequence, i.e. `ctrl+x ctrl+c`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeySequence(Vec<KeyCombo>);
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(|combo| combo.to_string()).collect();
+        f.write_str(&keys.join(" "))
+    }
+}
+
+impl From<KeyCombo> for KeySequence {
+    fn from(combo: KeyCombo) -> Self {
+        KeySequence(vec![combo])
+    }
+}
+
+/// Text to type and actions to run for a key press or an expired sequence
+#[derive(Default)]
+pub struct Outcome {
+    /// Typed before the actions are run
+    pub text: String,
+    pub actions: Vec<Action>,
+}
+
+impl From<Vec<Action>> for Outcome {
+    fn from(actions: Vec<Action>) -> Self {
+        Outcome {
+            text: String::new(),
+            actions,
+        }
+    }
 }
 
 impl Keybindings {
-    pub fn get(&self, modifiers: &ModifiersState, keysym: u32) -> Option<&Action> {
-        self.inner.get(&KeyCombo {
-            modifiers: Modifiers(*modifiers),
-            key: keysym,
+    /// What a key press typing `utf8` does, `None` if the key is not bound and can be typed.
+    /// Keys that start or continue a sequence give no actions until it is complete.
+    pub fn get(
+        &mut self,
+        modifiers: &ModifiersState,
+        keysym: u32,
+        keycode: u32,
+        utf8: Option<&str>,
+    ) -> Option<Outcome> {
+        let candidates = candidates(modifiers, keysym, keycode);
+        let mut outcome = Outcome::default();
+        if !self.pending.is_empty() {
+            // Modifiers are pressed on their own while typing a sequence
+            if is_modifier(keysym) {
+                return Some(outcome);
+            }
+            if let Some((combo, action, continues)) =
+                self.node().and_then(|node| node.find(&candidates))
+            {
+                self.push(combo, utf8);
+                return Some(self.enter(action, continues).into());
+            }
+            // Leaving an unfinished sequence triggers its action or types its keys
+            outcome = self.cancel();
+            // Escape gives the sequence up without typing it
+            if keysym == keysyms::XKB_KEY_Escape {
+                outcome.text.clear();
+                return Some(outcome);
+            }
+        }
+        match self.root.find(&candidates) {
+            Some((combo, action, continues)) => {
+                self.push(combo, utf8);
+                outcome.actions.extend(self.enter(action, continues));
+                Some(outcome)
+            }
+            None if outcome.actions.is_empty() && outcome.text.is_empty() => None,
+            None => {
+                outcome.text.extend(utf8);
+                Some(outcome)
+            }
+        }
+    }
+
+    /// Time until the current sequence is given up
+    pub fn timeout(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Gives up a sequence that has not been continued in time, with its action if it has one
+    /// and otherwise the text of its keys
+    pub fn expire(&mut self) -> Option<Outcome> {
+        match self.deadline {
+            Some(deadline) if deadline <= Instant::now() => Some(self.cancel()),
+            _ => None,
+        }
+    }
+
+    /// The keys of an unfinished sequence, like `ctrl+x`
+    pub fn pending(&self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let keys: Vec<String> = self.pending.iter().map(|combo| combo.to_string()).collect();
+        Some(keys.join(" "))
+    }
+
+    fn node(&self) -> Option<&Node> {
+        self.pending
+            .iter()
+            .try_fold(&self.root, |node, combo| node.next.get(combo))
+    }
+
+    fn push(&mut self, combo: KeyCombo, utf8: Option<&str>) {
+        self.pending.push(combo);
+        self.text.extend(utf8);
+    }
+
+    /// Waits for the next key if the sequence continues, otherwise finishes it
+    fn enter(&mut self, action: Option<Action>, continues: bool) -> Vec<Action> {
+        if continues {
+            self.deadline = Some(Instant::now() + self.timeout);
+            vec![]
+        } else {
+            self.pending.clear();
+            self.text.clear();
+            self.deadline = None;
+            action.into_iter().collect()
+        }
+    }
+
+    /// Ends the current sequence with its action, or with the text of its keys if it has none
+    fn cancel(&mut self) -> Outcome {
+        let action = self.node().and_then(|node| node.action);
+        let text = std::mem::take(&mut self.text);
+        self.pending.clear();
+        self.deadline = None;
+        match action {
+            Some(action) => vec![action].into(),
+            None => Outcome {
+                text,
+                actions: vec![],
+            },
+        }
+    }
+
+    fn is_bound(&self, sequence: &KeySequence) -> bool {
+        sequence
+            .0
+            .iter()
+            .try_fold(&self.root, |node, combo| node.next.get(combo))
+            .is_some_and(|node| node.action.is_some())
+    }
+
+    fn add_sequences(&mut self, action: Action, sequences: &[KeySequence]) {
+        for sequence in sequences {
+            let node = sequence.0.iter().fold(&mut self.root, |node, combo| {
+                node.next.entry(combo.to_owned()).or_default()
+            });
+            node.action = Some(action);
+        }
+    }
+}
+
+impl Node {
+    /// The first of `candidates` that is bound, with its action and whether a sequence continues
+    fn find(&self, candidates: &[KeyCombo]) -> Option<(KeyCombo, Option<Action>, bool)> {
+        candidates.iter().find_map(|combo| {
+            self.next
+                .get(combo)
+                .map(|node| (combo.clone(), node.action, !node.next.is_empty()))
         })
     }
+}
+
+/// The ways a key press can be bound, first by its symbol, then as physical key and finally
+/// by the symbol of a latin layout, for shortcuts typed with another alphabet
+fn candidates(modifiers: &ModifiersState, keysym: u32, keycode: u32) -> Vec<KeyCombo> {
+    let modifiers = Modifiers(*modifiers);
+    [
+        Some(Key::Sym(keysym)),
+        Some(Key::Code(keycode)),
+        latin_keysym(keysym, keycode, modifiers.0.shift).map(Key::Sym),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|key| KeyCombo {
+        modifiers: modifiers.clone(),
+        key,
+    })
+    .collect()
+}
 
-    fn add_key_combos(&mut self, action: Action, key_combos: &[KeyCombo]) {
-        key_combos.iter().for_each(|entry| {
-            self.inner.insert(entry.to_owned(), action);
-        });
+/// Keysyms of the US layout by evdev keycode, from `1` to `/`
+const LATIN_KEYS: [(u32, u32); 47] = [
+    (2, keysyms::XKB_KEY_1),
+    (3, keysyms::XKB_KEY_2),
+    (4, keysyms::XKB_KEY_3),
+    (5, keysyms::XKB_KEY_4),
+    (6, keysyms::XKB_KEY_5),
+    (7, keysyms::XKB_KEY_6),
+    (8, keysyms::XKB_KEY_7),
+    (9, keysyms::XKB_KEY_8),
+    (10, keysyms::XKB_KEY_9),
+    (11, keysyms::XKB_KEY_0),
+    (12, keysyms::XKB_KEY_minus),
+    (13, keysyms::XKB_KEY_equal),
+    (16, keysyms::XKB_KEY_q),
+    (17, keysyms::XKB_KEY_w),
+    (18, keysyms::XKB_KEY_e),
+    (19, keysyms::XKB_KEY_r),
+    (20, keysyms::XKB_KEY_t),
+    (21, keysyms::XKB_KEY_y),
+    (22, keysyms::XKB_KEY_u),
+    (23, keysyms::XKB_KEY_i),
+    (24, keysyms::XKB_KEY_o),
+    (25, keysyms::XKB_KEY_p),
+    (26, keysyms::XKB_KEY_bracketleft),
+    (27, keysyms::XKB_KEY_bracketright),
+    (30, keysyms::XKB_KEY_a),
+    (31, keysyms::XKB_KEY_s),
+    (32, keysyms::XKB_KEY_d),
+    (33, keysyms::XKB_KEY_f),
+    (34, keysyms::XKB_KEY_g),
+    (35, keysyms::XKB_KEY_h),
+    (36, keysyms::XKB_KEY_j),
+    (37, keysyms::XKB_KEY_k),
+    (38, keysyms::XKB_KEY_l),
+    (39, keysyms::XKB_KEY_semicolon),
+    (40, keysyms::XKB_KEY_apostrophe),
+    (41, keysyms::XKB_KEY_grave),
+    (43, keysyms::XKB_KEY_backslash),
+    (44, keysyms::XKB_KEY_z),
+    (45, keysyms::XKB_KEY_x),
+    (46, keysyms::XKB_KEY_c),
+    (47, keysyms::XKB_KEY_v),
+    (48, keysyms::XKB_KEY_b),
+    (49, keysyms::XKB_KEY_n),
+    (50, keysyms::XKB_KEY_m),
+    (51, keysyms::XKB_KEY_comma),
+    (52, keysyms::XKB_KEY_period),
+    (53, keysyms::XKB_KEY_slash),
+];
+
+/// The keysym of the key on a US layout, if the current layout does not use latin letters.
+/// Latin layouts like Dvorak are left alone, as shortcuts follow their letters elsewhere too.
+pub fn latin_keysym(keysym: u32, keycode: u32, shift: bool) -> Option<u32> {
+    // Latin 1 and function keys
+    if keysym < 0x100 || (0xfd00..=0xffff).contains(&keysym) || keysym >= 0x1008fe00 {
+        return None;
     }
+    let evdev = keycode.checked_sub(8)?;
+    let (_, latin) = LATIN_KEYS.iter().find(|(code, _)| *code == evdev)?;
+    // Letters are upper case with shift, like the keysyms bindings are written with
+    Some(match *latin {
+        keysyms::XKB_KEY_a..=keysyms::XKB_KEY_z if shift => latin - 0x20,
+        latin => latin,
+    })
+}
+
+/// Shift, control, alt and the like
+fn is_modifier(keysym: u32) -> bool {
+    (keysyms::XKB_KEY_Shift_L..=keysyms::XKB_KEY_Hyper_R).contains(&keysym)
+        || keysym == keysyms::XKB_KEY_ISO_Level3_Shift
 }
 
 impl KeyCombo {
-    pub fn new(modifiers: Modifiers, key: u32) -> Self {
-        KeyCombo { modifiers, key }
+    pub fn new(modifiers: Modifiers, keysym: u32) -> Self {
+        KeyCombo {
+            modifiers,
+            key: Key::Sym(keysym),
+        }
+    }
+
+    /// Reads modifiers followed by a key, all joined by `+`
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut modifiers = ModifiersState::default();
+        let mut tokens: Vec<&str> = value.split('+').collect();
+        let key = tokens.pop().unwrap_or_default();
+        for token in tokens {
+            match token.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "logo" | "super" | "win" => modifiers.logo = true,
+                _ => {
+                    let known = MODIFIERS.iter().copied();
+                    return Err(unknown("modifier", token, value, known));
+                }
+            }
+        }
+        if key.is_empty() || MODIFIERS.contains(&key.to_lowercase().as_str()) {
+            return Err(format!("No key given in '{}'", value));
+        }
+        if let Some(code) = key.strip_prefix("code:") {
+            return match code.parse() {
+                Ok(code) => Ok(KeyCombo {
+                    modifiers: Modifiers(modifiers),
+                    key: Key::Code(code),
+                }),
+                Err(_) => Err(format!("Invalid keycode '{}' in '{}'", code, value)),
+            };
+        }
+        match keysym_by_name(key) {
+            Some(keysym) => Ok(KeyCombo::new(Modifiers(modifiers), keysym)),
+            None => {
+                let known = MODIFIERS
+                    .iter()
+                    .chain(ALIASES.iter().map(|(alias, _)| alias))
+                    .copied()
+                    .chain(key_names().values().copied());
+                Err(unknown("key", key, value, known))
+            }
+        }
+    }
+}
+
+const MODIFIERS: [&str; 4] = ["ctrl", "shift", "alt", "logo"];
+
+/// Common names that differ from the keysym names
+const ALIASES: [(&str, &str); 8] = [
+    ("enter", "Return"),
+    ("esc", "Escape"),
+    ("del", "Delete"),
+    ("ins", "Insert"),
+    ("pageup", "Prior"),
+    ("pgup", "Prior"),
+    ("pagedown", "Next"),
+    ("pgdown", "Next"),
+];
+
+/// Looks up a keysym by its name, ignoring the case of names longer than one letter
+fn keysym_by_name(name: &str) -> Option<u32> {
+    if let Some(record) = lookup_by_name(name) {
+        return Some(record.keysym);
+    }
+    // Single letters are upper case with shift and lower case without
+    if name.chars().count() == 1 {
+        return None;
+    }
+    let lower = name.to_lowercase();
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, name)| *name)
+        .or_else(|| key_names().get(&lower).copied())?;
+    lookup_by_name(name).map(|record| record.keysym)
+}
+
+/// Names of the keysyms by their lower case version, the crate offers no way to list them
+fn key_names() -> &'static HashMap<String, &'static str> {
+    static NAMES: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        // Characters, function keys and XF86 media keys
+        (0x20..=0x20ff)
+            .chain(0xfe00..=0xffff)
+            .chain(0x1008fe00..=0x1008ffff)
+            .filter_map(lookup_by_keysym)
+            .flat_map(|record| record.names.iter())
+            // Letters are matched exactly
+            .filter(|name| name.chars().count() > 1)
+            .map(|name| (name.to_lowercase(), *name))
+            .collect()
+    })
+}
+
+/// Error for an unknown token with the closest known name as suggestion
+fn unknown<'a>(
+    kind: &str,
+    token: &str,
+    value: &str,
+    known: impl Iterator<Item = &'a str>,
+) -> String {
+    let lower = token.to_lowercase();
+    let suggestion = known
+        .map(|name| (distance(&lower, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= 2.max(lower.len() / 3))
+        .min_by_key(|(distance, _)| *distance);
+    match suggestion {
+        Some((_, name)) => format!(
+            "Unknown {} '{}' in '{}', did you mean {}?",
+            kind, token, value, name
+        ),
+        None => format!("Unknown {} '{}' in '{}'", kind, token, value),
+    }
+}
+
+/// Levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let modifiers = &self.modifiers.0;
+        for (pressed, name) in [
+            (modifiers.ctrl, "ctrl"),
+            (modifiers.alt, "alt"),
+            (modifiers.shift, "shift"),
+            (modifiers.logo, "logo"),
+        ] {
+            if pressed {
+                write!(f, "{}+", name)?;
+            }
+        }
+        let keysym = match self.key {
+            Key::Sym(keysym) => keysym,
+            Key::Code(code) => return write!(f, "code:{}", code),
+        };
+        match lookup_by_keysym(keysym).and_then(|record| record.names.first()) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#x}", keysym),
+        }
     }
 }
 
-impl<'de> Deserialize<'de> for KeyCombo {
+impl<'de> Deserialize<'de> for KeySequence {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_str(KeyComboVisitor)
+        deserializer.deserialize_str(KeySequenceVisitor)
     }
 }
 
-struct KeyComboVisitor;
-impl<'de> Visitor<'de> for KeyComboVisitor {
-    type Value = KeyCombo;
+struct KeySequenceVisitor;
+impl<'de> Visitor<'de> for KeySequenceVisitor {
+    type Value = KeySequence;
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("assignments of key combinations")
+        formatter.write_str("key combinations separated by spaces")
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        let mut modifiers = ModifiersState::default();
-        let mut key: Option<u32> = None;
-        value.split('+').for_each(|s| match s {
-            "ctrl" => modifiers.ctrl = true,
-            "shift" => modifiers.shift = true,
-            "alt" => modifiers.alt = true,
-            "logo" => modifiers.logo = true,
-            s => {
-                if let Some(value) = lookup_by_name(s) {
-                    key = Some(value.keysym);
-                }
-            }
-        });
-        if let Some(key) = key {
-            Ok(KeyCombo {
-                modifiers: Modifiers(modifiers),
-                key,
-            })
-        } else {
-            Err(de::Error::custom("No key given or unable to parse"))
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let combos = value
+            .split_whitespace()
+            .map(KeyCombo::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if combos.is_empty() {
+            return Err("No key given".to_owned());
         }
+        Ok(KeySequence(combos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keybindings(sequence: &str, action: Action) -> Keybindings {
+        let mut keybindings = Keybindings::from(KeybindingsConfig::default());
+        keybindings.add_sequences(action, &[sequence.parse().unwrap()]);
+        keybindings
+    }
+
+    fn press(keybindings: &mut Keybindings, keysym: u32, text: &str) -> Option<Outcome> {
+        keybindings.get(&ModifiersState::default(), keysym, 0, Some(text))
+    }
+
+    #[test]
+    fn unbound_sequence_types_its_keys() {
+        let mut keybindings = keybindings("g g", Action::First);
+        let outcome = press(&mut keybindings, keysyms::XKB_KEY_g, "g").unwrap();
+        assert!(outcome.text.is_empty() && outcome.actions.is_empty());
+        let outcome = press(&mut keybindings, keysyms::XKB_KEY_x, "x").unwrap();
+        assert_eq!(outcome.text, "gx");
+        assert!(outcome.actions.is_empty());
+        assert!(keybindings.pending().is_none());
+    }
+
+    #[test]
+    fn expired_sequence_types_its_keys() {
+        let mut keybindings = keybindings("g g", Action::First);
+        press(&mut keybindings, keysyms::XKB_KEY_g, "g");
+        keybindings.deadline = Some(Instant::now());
+        let outcome = keybindings.expire().unwrap();
+        assert_eq!(outcome.text, "g");
+        assert!(outcome.actions.is_empty());
+    }
+
+    #[test]
+    fn finished_sequence_types_nothing() {
+        let mut keybindings = keybindings("g g", Action::First);
+        press(&mut keybindings, keysyms::XKB_KEY_g, "g");
+        let outcome = press(&mut keybindings, keysyms::XKB_KEY_g, "g").unwrap();
+        assert!(outcome.text.is_empty());
+        assert!(matches!(outcome.actions[..], [Action::First]));
     }
 }
@@ -0,0 +1,658 @@
+use image::{imageops, RgbaImage};
+use std::cmp;
+
+use crate::color::Color;
+use crate::config::{ColorConfig, DescriptionPosition, GridConfig, Layout, StyleConfig};
+use crate::draw;
+use crate::font::Font;
+use crate::icons::Icons;
+use crate::selection::Element;
+
+/// Everything needed to draw onto the current frame
+pub struct Frame<'a> {
+    pub img: &'a mut RgbaImage,
+    pub font: &'a mut Font,
+    pub description_font: &'a mut Font,
+    pub colors: &'a ColorConfig,
+    pub style: &'a StyleConfig,
+    pub font_size: f32,
+    pub scale: f32,
+    /// Result under the pointer
+    pub hover: Option<usize>,
+}
+
+/// Rectangle in buffer pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Area {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Area {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Arrangement of the search results in the window
+pub trait ResultsLayout {
+    /// Draws the results into `area`, the padded window whose first line holds
+    /// the prompt and query, taking up `query_width` pixels.
+    fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Area,
+        query_width: u32,
+        results: &[&Element],
+        selection: Option<usize>,
+    );
+
+    /// Area behind the prompt and query, within the first line of `area`
+    fn query_box(&self, frame: &Frame, area: Area, _query_width: u32) -> Area {
+        Area {
+            height: frame.line_height(),
+            ..area
+        }
+    }
+
+    /// Where the results were drawn in the last frame
+    fn hits(&self) -> &[(Area, usize)];
+
+    /// Number of results shown at once, how far a page up or down moves
+    fn page_size(&self) -> usize {
+        self.hits().len()
+    }
+
+    /// Index of the result drawn at the given position in buffer pixels
+    fn result_at(&self, x: u32, y: u32) -> Option<usize> {
+        self.hits()
+            .iter()
+            .find(|(area, _)| area.contains(x, y))
+            .map(|(_, index)| *index)
+    }
+
+    /// Moves the selection among `len` results, `None` when moving before the first one
+    fn navigate(&self, selection: usize, direction: Direction, len: usize) -> Option<usize> {
+        match direction {
+            Direction::Up | Direction::Left => selection.checked_sub(1),
+            Direction::Down | Direction::Right => Some((selection + 1).min(len.saturating_sub(1))),
+        }
+    }
+}
+
+pub fn new(layout: Layout, grid: &GridConfig) -> Box<dyn ResultsLayout> {
+    match layout {
+        Layout::Vertical => Box::new(Vertical {
+            hits: Vec::new(),
+            page: 1,
+        }),
+        Layout::Horizontal => Box::new(Horizontal { hits: Vec::new() }),
+        Layout::Grid => Box::new(Grid {
+            config: grid.clone(),
+            icons: Icons::new(),
+            hits: Vec::new(),
+            page: 1,
+        }),
+    }
+}
+
+impl Frame<'_> {
+    pub fn line_height(&self) -> u32 {
+        (self.font_size * self.style.line_height) as u32
+    }
+
+    /// Distance between the tops of two results
+    fn row_height(&self) -> u32 {
+        self.line_height() + self.scaled(self.style.row_spacing)
+    }
+
+    /// Offset from the top of a line to the text, so it is centered vertically
+    pub fn text_offset(&self) -> u32 {
+        self.line_height().saturating_sub(self.font.line_height()) / 2
+    }
+
+    pub fn row_padding(&self) -> u32 {
+        self.scaled(self.style.row_padding)
+    }
+
+    /// Converts a size from the config into buffer pixels
+    pub fn scaled(&self, pixels: u32) -> u32 {
+        (pixels as f32 * self.scale) as u32
+    }
+
+    fn text_color(&self, selected: bool) -> &Color {
+        if selected {
+            &self.colors.text_selected
+        } else {
+            &self.colors.text
+        }
+    }
+
+    /// The part of the window below the query, with a separator drawn in between
+    fn below_query(&mut self, area: Area) -> Area {
+        let line_height = self.line_height();
+        let spacer = line_height + (0.3 * self.font_size) as u32;
+        let separator_width = self.scaled(self.style.separator_width);
+        draw::fill_rect(
+            self.img,
+            area.x,
+            area.y + line_height + (spacer - line_height).saturating_sub(separator_width) / 2,
+            area.width,
+            separator_width,
+            &self.colors.separator,
+        );
+        Area {
+            y: area.y + spacer,
+            height: area.height.saturating_sub(spacer),
+            ..area
+        }
+    }
+
+    /// Highlights the result if it is selected or under the pointer
+    fn result_background(
+        &mut self,
+        index: usize,
+        selection: Option<usize>,
+        (x, y, width, height): (u32, u32, u32, u32),
+    ) {
+        let color = if selection == Some(index) {
+            &self.colors.selection_background
+        } else if self.hover == Some(index) {
+            &self.colors.hover_background
+        } else {
+            return;
+        };
+        draw::fill_rounded_rect(
+            self.img,
+            x,
+            y,
+            width,
+            height,
+            self.style.selection_radius * self.scale,
+            color,
+        );
+    }
+
+    /// Draws a scrollbar at the right edge of `area` if the `total` rows do not fit,
+    /// returning the space left for the results
+    fn scrollbar(&mut self, area: Area, offset: usize, visible: usize, total: usize) -> Area {
+        if !self.style.scrollbar {
+            return area;
+        }
+        let width = self.scaled(self.style.scrollbar_width).max(1);
+        if visible < total {
+            let total = total as u64;
+            let height = area.height as u64;
+            let thumb_height = (height * visible as u64 / total)
+                .max(2 * width as u64)
+                .min(height);
+            let thumb_y = (height * offset as u64 / total).min(height - thumb_height);
+            draw::fill_rounded_rect(
+                self.img,
+                area.x + area.width.saturating_sub(width),
+                area.y + thumb_y as u32,
+                width,
+                thumb_height as u32,
+                width as f32 / 2.,
+                &self.colors.scrollbar,
+            );
+        }
+        Area {
+            width: area.width.saturating_sub(2 * width),
+            ..area
+        }
+    }
+
+    /// Draws the number of the `n`-th visible result if enabled, right aligned to `right`
+    /// and on the baseline of a line starting at `y`
+    fn number_hint(&mut self, n: usize, right: u32, y: u32) {
+        if !self.style.number_hints || n > 9 {
+            return;
+        }
+        let hint = n.to_string();
+        let color = self.colors.description.clone();
+        let x = right.saturating_sub(self.description_font.measure(&hint));
+        let y = (y + self.text_offset() + self.font.ascent())
+            .saturating_sub(self.description_font.ascent());
+        self.description_font.render(&hint, &color, self.img, x, y);
+    }
+
+    pub fn query_background(&mut self, area: Area) {
+        draw::fill_rounded_rect(
+            self.img,
+            area.x,
+            area.y,
+            area.width,
+            area.height,
+            self.style.query_radius * self.scale,
+            &self.colors.query_background,
+        );
+    }
+
+    /// Draws the window border and rounds the corners, the last step of every frame
+    pub fn finish(&mut self) {
+        let (width, height) = self.img.dimensions();
+        let radius = self.style.border_radius * self.scale;
+        draw::stroke_rounded_rect(
+            self.img,
+            0,
+            0,
+            width,
+            height,
+            radius,
+            self.scaled(self.style.border_width),
+            &self.colors.border,
+        );
+        draw::clip_corners(self.img, radius);
+    }
+}
+
+/// One result per line, keeping the selection in the middle when scrolling
+struct Vertical {
+    hits: Vec<(Area, usize)>,
+    page: usize,
+}
+
+impl ResultsLayout for Vertical {
+    fn hits(&self) -> &[(Area, usize)] {
+        &self.hits
+    }
+
+    fn page_size(&self) -> usize {
+        self.page
+    }
+
+    fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Area,
+        _query_width: u32,
+        results: &[&Element],
+        selection: Option<usize>,
+    ) {
+        self.hits.clear();
+        let area = frame.below_query(area);
+        let position = frame.style.description_position;
+        // Rows stay the same height, so scrolling does not jump around
+        let description_height = if position == DescriptionPosition::Below
+            && results.iter().any(|e| e.description.is_some())
+        {
+            frame.description_font.line_height()
+        } else {
+            0
+        };
+        let line_height = frame.line_height() + description_height;
+        let row_height = frame.row_height() + description_height;
+        let max_entries = (area.height / row_height.max(1)) as usize;
+        self.page = max_entries.max(1);
+        let selected = selection.unwrap_or(0);
+        let offset = selected.saturating_sub(max_entries / 2);
+        let visible = max_entries.min(results.len().saturating_sub(offset));
+        let area = frame.scrollbar(area, offset, visible, results.len());
+
+        for (i, matched) in results
+            .iter()
+            .enumerate()
+            .take(max_entries + offset)
+            .skip(offset)
+        {
+            let y = area.y + (i - offset) as u32 * row_height;
+            let row = Area {
+                x: area.x,
+                y,
+                width: area.width,
+                height: line_height,
+            };
+            self.hits.push((row, i));
+            frame.result_background(i, selection, (row.x, row.y, row.width, row.height));
+            let hint_padding = frame.row_padding().max(frame.font_size as u32 / 4);
+            frame.number_hint(
+                self.hits.len(),
+                (row.x + row.width).saturating_sub(hint_padding),
+                y,
+            );
+            let color = frame.text_color(selection == Some(i)).clone();
+            let x = area.x + frame.row_padding();
+            let text_y = y + frame.text_offset();
+            let (name_width, _) = frame
+                .font
+                .render(&matched.name, &color, frame.img, x, text_y);
+
+            if let Some(description) = &matched.description {
+                let color = frame.colors.description.clone();
+                let (x, y) = match position {
+                    // Aligned to the baseline of the name
+                    DescriptionPosition::Beside => (
+                        x + name_width + frame.font_size as u32 / 2,
+                        (text_y + frame.font.ascent())
+                            .saturating_sub(frame.description_font.ascent()),
+                    ),
+                    DescriptionPosition::Below => (x, y + frame.line_height()),
+                    DescriptionPosition::Hidden => continue,
+                };
+                frame
+                    .description_font
+                    .render(description, &color, frame.img, x, y);
+            }
+        }
+    }
+}
+
+/// All results on a single line like dmenu, split into pages that fit the available width.
+/// The page containing the selection is shown, with arrows hinting at further pages.
+struct Horizontal {
+    hits: Vec<(Area, usize)>,
+}
+
+impl ResultsLayout for Horizontal {
+    fn hits(&self) -> &[(Area, usize)] {
+        &self.hits
+    }
+
+    fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Area,
+        query_width: u32,
+        results: &[&Element],
+        selection: Option<usize>,
+    ) {
+        self.hits.clear();
+        let offset = query_offset(frame, area, query_width);
+        let gap = frame.font_size as u32;
+        let separator_width = frame.scaled(frame.style.separator_width);
+        draw::fill_rect(
+            frame.img,
+            area.x + offset.saturating_sub((gap + separator_width) / 2),
+            area.y,
+            separator_width,
+            frame.line_height(),
+            &frame.colors.separator,
+        );
+        let area = Area {
+            x: area.x + offset,
+            y: area.y,
+            width: area.width.saturating_sub(offset),
+            height: frame.line_height(),
+        };
+        let text_y = area.y + frame.text_offset();
+        let arrow_width = frame.font.measure("<") + gap;
+        let available = area.width.saturating_sub(2 * arrow_width);
+        let widths: Vec<u32> = results
+            .iter()
+            .map(|e| frame.font.measure(&e.name) + gap)
+            .collect();
+
+        // Find the start of the page that contains the selection
+        let selected = selection.unwrap_or(0);
+        let mut page_start = 0;
+        let mut x = 0;
+        for (i, width) in widths.iter().enumerate().take(selected + 1) {
+            if x + width > available && i > page_start {
+                page_start = i;
+                x = 0;
+            }
+            x += width;
+        }
+
+        let arrow_color = frame.colors.prompt.clone();
+        if page_start > 0 {
+            frame
+                .font
+                .render("<", &arrow_color, frame.img, area.x, text_y);
+        }
+
+        let mut x = 0;
+        for (i, matched) in results.iter().enumerate().skip(page_start) {
+            if x + widths[i] > available && i > page_start {
+                frame.font.render(
+                    ">",
+                    &arrow_color,
+                    frame.img,
+                    area.x + area.width - arrow_width + gap,
+                    text_y,
+                );
+                break;
+            }
+            let item = Area {
+                x: area.x + arrow_width + x - gap / 2,
+                y: area.y,
+                width: widths[i],
+                height: area.height,
+            };
+            self.hits.push((item, i));
+            frame.result_background(i, selection, (item.x, item.y, item.width, item.height));
+            let color = frame.text_color(selection == Some(i)).clone();
+            frame.font.render(
+                &matched.name,
+                &color,
+                frame.img,
+                area.x + arrow_width + x,
+                text_y,
+            );
+            x += widths[i];
+        }
+    }
+
+    fn query_box(&self, frame: &Frame, area: Area, query_width: u32) -> Area {
+        Area {
+            width: query_offset(frame, area, query_width)
+                .saturating_sub(frame.font_size as u32 / 2),
+            height: frame.line_height(),
+            ..area
+        }
+    }
+}
+
+/// Like dmenu, the query takes up a third of the line unless it is longer
+fn query_offset(frame: &Frame, area: Area, query_width: u32) -> u32 {
+    cmp::max(
+        query_width + 2 * frame.row_padding() + frame.font_size as u32,
+        area.width / 3,
+    )
+}
+
+/// Tiles with an icon and the name below, like an app drawer
+struct Grid {
+    config: GridConfig,
+    icons: Icons,
+    hits: Vec<(Area, usize)>,
+    page: usize,
+}
+
+impl ResultsLayout for Grid {
+    fn hits(&self) -> &[(Area, usize)] {
+        &self.hits
+    }
+
+    fn page_size(&self) -> usize {
+        self.page
+    }
+
+    fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Area,
+        _query_width: u32,
+        results: &[&Element],
+        selection: Option<usize>,
+    ) {
+        self.hits.clear();
+        let area = frame.below_query(area);
+        let columns = self.config.columns.max(1);
+        let line_height = frame.line_height();
+        let gap = (frame.font_size / 4.) as u32;
+        let icon_size = (self.config.icon_size as f32 * frame.scale) as u32;
+        let tile_size = (self.config.tile_size as f32 * frame.scale) as u32;
+        let tile_height = tile_size
+            .max(icon_size + 3 * gap + self.config.label_lines as u32 * line_height)
+            .max(1);
+        let rows = ((area.height / tile_height) as usize).max(1);
+        let selected = selection.unwrap_or(0);
+        // Scroll by rows, keeping the selected row in the middle
+        let first_row = (selected / columns).saturating_sub(rows / 2);
+        let total_rows = results.len().div_ceil(columns);
+        let visible = rows.min(total_rows.saturating_sub(first_row));
+        let area = frame.scrollbar(area, first_row, visible, total_rows);
+        self.page = rows * columns;
+        let tile_width = tile_size.min(area.width / columns as u32);
+        let outline = (2. * frame.scale).max(1.) as u32;
+        let radius = frame.style.selection_radius * frame.scale;
+
+        let left = area.x + (area.width - tile_width * columns as u32) / 2;
+
+        for (i, element) in results
+            .iter()
+            .enumerate()
+            .skip(first_row * columns)
+            .take(rows * columns)
+        {
+            let x = left + (i % columns) as u32 * tile_width;
+            let y = area.y + (i / columns - first_row) as u32 * tile_height;
+            let is_selected = selection == Some(i);
+            self.hits.push((
+                Area {
+                    x,
+                    y,
+                    width: tile_width,
+                    height: tile_height,
+                },
+                i,
+            ));
+            frame.result_background(i, selection, (x, y, tile_width, tile_height));
+            if is_selected {
+                // Outline the tile if there is no background to show the selection
+                if frame.colors.selection_background.3 == 0 {
+                    draw::stroke_rounded_rect(
+                        frame.img,
+                        x,
+                        y,
+                        tile_width,
+                        tile_height,
+                        radius,
+                        outline,
+                        &frame.colors.text_selected,
+                    );
+                }
+            }
+
+            frame.number_hint(self.hits.len(), (x + tile_width).saturating_sub(gap), y);
+
+            let icon_x = x + tile_width.saturating_sub(icon_size) / 2;
+            let icon_y = y + gap;
+            match self.icons.get(&element.name, icon_size) {
+                Some(icon) => imageops::overlay(frame.img, icon, icon_x.into(), icon_y.into()),
+                None => {
+                    // Without an icon, show the first letter of the name instead
+                    draw::fill_rect(
+                        frame.img,
+                        icon_x,
+                        icon_y,
+                        icon_size,
+                        icon_size,
+                        &frame.colors.query_selection,
+                    );
+                    let letter: String = element
+                        .name
+                        .chars()
+                        .take(1)
+                        .flat_map(char::to_uppercase)
+                        .collect();
+                    let letter_width = frame.font.measure(&letter);
+                    let color = frame.text_color(is_selected).clone();
+                    frame.font.render(
+                        &letter,
+                        &color,
+                        frame.img,
+                        icon_x + icon_size.saturating_sub(letter_width) / 2,
+                        icon_y + icon_size.saturating_sub(frame.font.line_height()) / 2,
+                    );
+                }
+            }
+
+            let color = frame.text_color(is_selected).clone();
+            let label_width = tile_width.saturating_sub(2 * gap);
+            let label_y = icon_y + icon_size + gap;
+            for (n, line) in wrap(
+                frame.font,
+                &element.name,
+                label_width,
+                self.config.label_lines,
+            )
+            .iter()
+            .enumerate()
+            {
+                let line_width = frame.font.measure(line);
+                frame.font.render(
+                    line,
+                    &color,
+                    frame.img,
+                    x + tile_width.saturating_sub(line_width) / 2,
+                    label_y + n as u32 * line_height + frame.text_offset(),
+                );
+            }
+        }
+    }
+
+    fn navigate(&self, selection: usize, direction: Direction, len: usize) -> Option<usize> {
+        let columns = self.config.columns.max(1);
+        let last = len.saturating_sub(1);
+        match direction {
+            Direction::Up => selection.checked_sub(columns),
+            // Moving down onto an incomplete last row ends on its last tile
+            Direction::Down if selection / columns < last / columns => {
+                Some((selection + columns).min(last))
+            }
+            Direction::Down => Some(selection),
+            Direction::Left => Some(selection.saturating_sub(1)),
+            Direction::Right => Some((selection + 1).min(last)),
+        }
+    }
+}
+
+/// Breaks `text` into at most `max_lines` lines fitting into `width`, preferably after
+/// spaces and punctuation. Text that does not fit is cut off with an ellipsis.
+fn wrap(font: &Font, text: &str, width: u32, max_lines: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() && lines.len() < max_lines {
+        // Longest prefix that fits, but at least one character
+        let mut end = 0;
+        let mut word_end = None;
+        for (i, c) in rest.char_indices() {
+            let next = i + c.len_utf8();
+            if end > 0 && font.measure(&rest[..next]) > width {
+                break;
+            }
+            end = next;
+            if matches!(c, ' ' | '-' | '_' | '.') {
+                word_end = Some(next);
+            }
+        }
+
+        if end < rest.len() {
+            if lines.len() + 1 == max_lines {
+                let mut line = rest[..end].to_owned();
+                while !line.is_empty() && font.measure(&format!("{}…", line)) > width {
+                    line.pop();
+                }
+                lines.push(format!("{}…", line.trim_end()));
+                break;
+            }
+            end = word_end.unwrap_or(end);
+        }
+        lines.push(rest[..end].trim_end().to_owned());
+        rest = rest[end..].trim_start();
+    }
+    lines
+}
@@ -1,2 +1,4 @@
+pub mod aliases;
+pub mod desktop;
 pub mod history;
 pub mod selection;
@@ -1,46 +1,99 @@
-use crate::config::Config;
+use crate::background::Background;
+use crate::config::{ClickMode, Config, Layout};
 use crate::gui::{Action, DData, RenderEvent};
+use crate::layout::{Area, Direction};
 use clap::Parser;
 use history::History;
-use image::ImageBuffer;
 use log::*;
 use nix::{
     sys::wait::{waitpid, WaitPidFlag, WaitStatus},
     unistd::{fork, ForkResult},
 };
 use notify_rust::Notification;
+use protocols::fractional_scale_v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1;
+use protocols::kde_blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
 use smithay_client_toolkit::{
     default_environment,
     environment::SimpleGlobal,
-    new_default_environment,
-    reexports::{calloop, protocols::wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1},
+    get_surface_outputs, new_default_environment,
+    output::with_output_info,
+    reexports::{
+        calloop,
+        client::protocol::{wl_compositor::WlCompositor, wl_output::WlOutput},
+        protocols::{
+            unstable::text_input::v3::client::zwp_text_input_manager_v3,
+            unstable::xdg_output::v1::client::zxdg_output_manager_v1::ZxdgOutputManagerV1,
+            viewporter::client::wp_viewporter,
+            wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1,
+        },
+    },
     WaylandSource,
 };
-use std::{cmp, error::Error, path::PathBuf, process, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    cmp,
+    error::Error,
+    path::PathBuf,
+    process,
+    rc::Rc,
+    time::Duration,
+};
 use tokio::task::JoinHandle;
 
+mod aliases;
+mod background;
 mod color;
 mod config;
+mod desktop;
+mod draw;
 mod font;
 mod gui;
 mod history;
+mod icons;
+mod ime;
 mod keybinds;
+mod layout;
+mod modes;
+mod outputs;
+mod pointer;
+mod protocols;
 mod selection;
+mod theme;
+mod watch;
 
 default_environment!(Env,
     fields = [
         layer_shell: SimpleGlobal<zwlr_layer_shell_v1::ZwlrLayerShellV1>,
+        text_input_manager: SimpleGlobal<zwp_text_input_manager_v3::ZwpTextInputManagerV3>,
+        viewporter: SimpleGlobal<wp_viewporter::WpViewporter>,
+        fractional_scale_manager: SimpleGlobal<WpFractionalScaleManagerV1>,
+        xdg_output_manager: SimpleGlobal<ZxdgOutputManagerV1>,
+        blur_manager: SimpleGlobal<OrgKdeKwinBlurManager>,
     ],
     singles = [
-        zwlr_layer_shell_v1::ZwlrLayerShellV1 => layer_shell
+        zwlr_layer_shell_v1::ZwlrLayerShellV1 => layer_shell,
+        zwp_text_input_manager_v3::ZwpTextInputManagerV3 => text_input_manager,
+        wp_viewporter::WpViewporter => viewporter,
+        WpFractionalScaleManagerV1 => fractional_scale_manager,
+        ZxdgOutputManagerV1 => xdg_output_manager,
+        OrgKdeKwinBlurManager => blur_manager
     ],
 );
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
+    /// Config file merged on top of the system and user config, can be given multiple times
+    #[clap(short, long)]
+    config: Vec<PathBuf>,
+
+    /// Override a config value, i.e. -o colors.background='#000000ff'
+    #[clap(short = 'o', long = "option", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+
+    /// Use the settings of the [profile.<name>] section of the config
     #[clap(short, long)]
-    config: Option<PathBuf>,
+    profile: Option<String>,
 
     /// Read list from stdin instead of PATH
     #[clap(long)]
@@ -53,6 +106,14 @@ struct Args {
     #[clap(long)]
     from_file: Vec<PathBuf>,
 
+    /// Include aliases and functions of your shell, enabled with PATH if set in config
+    #[clap(long)]
+    from_aliases: bool,
+
+    /// Read list from the desktop entries of installed applications
+    #[clap(long)]
+    from_desktop: bool,
+
     /// Output selection to stdout instead of executing it
     #[clap(long)]
     stdout: bool,
@@ -60,6 +121,14 @@ struct Args {
     /// Set custom history name. Default history will only be used if stdin is not set
     #[clap(long)]
     history: Option<PathBuf>,
+
+    /// Output to show kickoff on, by name or part of its description, or 'focused'
+    #[clap(long)]
+    output: Option<String>,
+
+    /// List the built-in themes and those in $XDG_CONFIG_HOME/kickoff/themes
+    #[clap(long)]
+    list_themes: bool,
 }
 
 #[tokio::main]
@@ -77,48 +146,96 @@ async fn run() -> Result<Option<JoinHandle<()>>, Box<dyn Error>> {
     env_logger::init();
 
     let args = Args::parse();
-
-    let config = match Config::load(args.config) {
-        Ok(c) => c,
-        Err(e) => {
-            error!("{}", e);
-            process::exit(1);
+    if args.list_themes {
+        for theme in theme::list() {
+            println!("{}", theme);
         }
-    };
+        return Ok(None);
+    }
+
+    // A broken config is reported in the window, so it can be fixed while kickoff is open
+    let (mut config, mut config_error) =
+        match Config::load(&args.config, &args.options, args.profile.as_deref()) {
+            Ok(c) => (c, None),
+            Err(e) => {
+                error!("{}", e);
+                (Config::fallback(), Some(e.to_string()))
+            }
+        };
+
+    let from_stdin = args.from_stdin || config.sources.stdin;
+    let from_desktop = args.from_desktop || config.sources.desktop;
+    let mut from_file = args.from_file.clone();
+    from_file.extend(config.sources.files.iter().cloned());
+    let history_file = args.history.clone().or_else(|| config.history.file.clone());
 
     let mut apps = selection::ElementListBuilder::new();
-    if args.from_path || (!args.from_stdin && args.from_file.is_empty()) {
+    let default_sources = !from_stdin && !from_desktop && from_file.is_empty();
+    if args.from_path || config.sources.path || default_sources {
         apps.add_path();
     }
-    if !args.from_file.is_empty() {
-        apps.add_files(&args.from_file);
+    if args.from_aliases || (default_sources && config.aliases.enabled) {
+        apps.add_aliases(config.aliases.file.clone());
+    }
+    if from_desktop {
+        apps.add_desktop();
+    }
+    if !from_file.is_empty() {
+        apps.add_files(&from_file);
     }
-    if args.from_stdin {
+    if from_stdin {
         apps.add_stdin();
     }
     let apps = apps.build();
 
-    let history = if (!args.from_stdin && args.from_file.is_empty()) || args.history.is_some() {
-        let path = args.history.clone();
+    let history = if (!from_stdin && from_file.is_empty()) || history_file.is_some() {
+        let path = history_file;
+        let backend = config.history.backend;
         let decrease_interval = config.history.decrease_interval;
         Some(tokio::task::spawn_blocking(move || {
-            History::load(path, decrease_interval)
+            History::load(path, backend, decrease_interval)
         }))
     } else {
         None
     };
 
-    let font = if let Some(font_name) = config.font {
-        let mut font_names = config.fonts.clone();
-        font_names.insert(0, font_name);
-        font::Font::new(font_names, config.font_size)
-    } else {
-        font::Font::new(config.fonts, config.font_size)
+    let background = {
+        let (style, colors) = (config.style.clone(), config.colors.clone());
+        tokio::task::spawn_blocking(move || Background::load(&style, &colors))
     };
 
-    let (env, display, queue) =
-        new_default_environment!(Env, fields = [layer_shell: SimpleGlobal::new(),])
-            .expect("Initial roundtrip failed!");
+    let font = font::Font::new(font_names(&config), config.font_size);
+
+    let (env, display, mut queue) = new_default_environment!(
+        Env,
+        fields = [
+            layer_shell: SimpleGlobal::new(),
+            text_input_manager: SimpleGlobal::new(),
+            viewporter: SimpleGlobal::new(),
+            fractional_scale_manager: SimpleGlobal::new(),
+            xdg_output_manager: SimpleGlobal::new(),
+            blur_manager: SimpleGlobal::new(),
+        ]
+    )
+    .expect("Initial roundtrip failed!");
+
+    let output = outputs::find(
+        env.get_all_outputs(),
+        env.get_global::<ZxdgOutputManagerV1>(),
+        &mut queue,
+        args.output.as_deref().unwrap_or(&config.output),
+    );
+    // Global names of the outputs the surface was last shown on
+    let surface_outputs: Rc<RefCell<Vec<u32>>> = Rc::default();
+    // Set when one of them is removed, until the surface shows up again
+    let output_removed = Rc::new(Cell::new(false));
+    let output_removed_handle = Rc::clone(&output_removed);
+    let surface_outputs_handle = Rc::clone(&surface_outputs);
+    let _output_listener = env.listen_for_outputs(move |_, info, _| {
+        if info.obsolete && surface_outputs_handle.borrow().contains(&info.id) {
+            output_removed_handle.set(true);
+        }
+    });
 
     let mut apps = apps.await?;
     let history = match history {
@@ -131,12 +248,57 @@ async fn run() -> Result<Option<JoinHandle<()>>, Box<dyn Error>> {
     };
     apps.sort_score();
 
-    let layer_shell = env.require_global::<zwlr_layer_shell_v1::ZwlrLayerShellV1>();
-    let pools = env
-        .create_double_pool(|_| {})
-        .expect("Failed to create a memory pool!");
-    let surface = env.create_surface().detach();
-    let mut surface = gui::Surface::new(None, surface, &layer_shell, pools);
+    let globals = gui::SurfaceGlobals {
+        layer_shell: env.require_global::<zwlr_layer_shell_v1::ZwlrLayerShellV1>(),
+        viewporter: env.get_global::<wp_viewporter::WpViewporter>(),
+        fractional_scale_manager: env.get_global::<WpFractionalScaleManagerV1>(),
+        compositor: env.require_global::<WlCompositor>(),
+        blur_manager: env.get_global::<OrgKdeKwinBlurManager>(),
+    };
+    // Follows the config, also for surfaces created after a reload
+    let shape = Cell::new(window_shape(&config));
+    // Changes to the window need a restart
+    let window = config.window.clone();
+    let create_surface = |output: Option<&WlOutput>| {
+        let pools = env
+            .create_double_pool(|_| {})
+            .expect("Failed to create a memory pool!");
+        let output_scale = Rc::new(Cell::new(None));
+        let output_scale_handle = Rc::clone(&output_scale);
+        surface_outputs.replace(
+            output
+                .and_then(|output| with_output_info(output, |info| info.id))
+                .into_iter()
+                .collect(),
+        );
+        output_removed.set(false);
+        let surface_outputs_handle = Rc::clone(&surface_outputs);
+        let output_removed_handle = Rc::clone(&output_removed);
+        let surface = env
+            .create_surface_with_scale_callback(move |scale, surface, _| {
+                output_scale_handle.set(Some(scale));
+                let outputs: Vec<u32> = get_surface_outputs(&surface)
+                    .iter()
+                    .filter_map(|output| with_output_info(output, |info| info.id))
+                    .collect();
+                // Leaving the last output happens when it is removed, which is when it is needed
+                if !outputs.is_empty() {
+                    surface_outputs_handle.replace(outputs);
+                    output_removed_handle.set(false);
+                }
+            })
+            .detach();
+        gui::Surface::new(
+            output,
+            surface,
+            output_scale,
+            pools,
+            &globals,
+            &window,
+            shape.get(),
+        )
+    };
+    let mut surface = create_surface(output.as_ref());
 
     let mut event_loop = calloop::EventLoop::<DData>::try_new().unwrap();
     WaylandSource::new(queue)
@@ -144,158 +306,504 @@ async fn run() -> Result<Option<JoinHandle<()>>, Box<dyn Error>> {
         .unwrap();
 
     gui::register_inputs(&env.get_all_seats(), &event_loop);
+    if let Err(e) = watch::register(&Config::dirs(&args.config), &event_loop) {
+        warn!("Config changes will not be applied until restart: {}", e);
+    }
+    if let Some(text_input_manager) =
+        env.get_global::<zwp_text_input_manager_v3::ZwpTextInputManagerV3>()
+    {
+        ime::register(&env.get_all_seats(), &text_input_manager);
+    }
 
     let mut search_results = apps.as_ref_vec();
+    let total = search_results.len();
     let mut need_redraw = false;
-    let mut data = DData::new(&display, config.keybindings.clone().into());
+    let mut results_layout = layout::new(config.layout, &config.grid);
+    let mut data = DData::new(&display, modes(&config));
     let mut selection = 0;
     let mut select_query = false;
     let mut font = font.await?;
+    let mut description_size = config.font_size * config.style.description_size;
+    let mut description_font = font.with_size(description_size);
+    let mut background = background.await?;
+    // Pointer state, positions in buffer pixels
+    let mut hover: Option<usize> = None;
+    let mut last_click: Option<(usize, u32)> = None;
+    let mut drag = 0;
+    let mut pending_keys = None;
+    let mut content_area = Area {
+        x: 0,
+        y: 0,
+        width: 0,
+        height: 0,
+    };
 
-    loop {
-        let gui::DData { query, action, .. } = &mut data;
+    'main: loop {
         match surface.next_render_event.take() {
-            Some(RenderEvent::Closed) => break,
+            Some(RenderEvent::Closed) => {
+                // The compositor closes the surface when its output gets unplugged,
+                // give it a moment to announce the removal before deciding to quit
+                event_loop
+                    .dispatch(Some(Duration::from_millis(100)), &mut data)
+                    .unwrap();
+                if output_removed.get() {
+                    info!("Output has been removed, moving to the focused output");
+                    surface = create_surface(None);
+                } else {
+                    break;
+                }
+            }
             Some(RenderEvent::Configure { width, height }) => {
                 need_redraw = surface.set_dimensions(width, height);
             }
             None => {}
         }
-        if let Some(action) = action.take() {
+        if surface.update_scale() {
+            font.set_size(config.font_size * surface.scale);
+            description_font.set_size(description_size * surface.scale);
+            need_redraw = true;
+        }
+        // Handling an action may queue another one in front, e.g. a click that executes
+        while let Some(action) = data.actions.pop_front() {
+            let to_buffer = |v: i32| (v.max(0) as f32 * surface.scale) as u32;
             match action {
-                Action::NavUp => {
+                Action::NavUp | Action::NavDown | Action::NavLeft | Action::NavRight => {
                     need_redraw = true;
-                    if selection > 0 {
-                        selection -= 1;
-                    } else if !query.is_empty() {
-                        select_query = true;
+                    let direction = match action {
+                        Action::NavUp => Direction::Up,
+                        Action::NavDown => Direction::Down,
+                        Action::NavLeft => Direction::Left,
+                        _ => Direction::Right,
+                    };
+                    if select_query {
+                        if direction == Direction::Down && !search_results.is_empty() {
+                            select_query = false;
+                        }
+                    } else if !search_results.is_empty() {
+                        match results_layout.navigate(selection, direction, search_results.len()) {
+                            Some(next) => selection = next,
+                            None if !data.query.is_empty() => select_query = true,
+                            None => {}
+                        }
                     }
                 }
-                Action::NavDown => {
+                Action::PageUp | Action::PageDown | Action::First | Action::Last
+                    if !search_results.is_empty() =>
+                {
+                    let page = results_layout.page_size().max(1);
+                    let last = search_results.len() - 1;
+                    selection = match action {
+                        Action::PageUp => selection.saturating_sub(page),
+                        Action::PageDown => cmp::min(selection + page, last),
+                        Action::First => 0,
+                        _ => last,
+                    };
+                    select_query = false;
                     need_redraw = true;
-                    if select_query && !search_results.is_empty() {
+                }
+                Action::Select(n) => {
+                    // The hits are from the last draw, the results may have changed since
+                    let hit = results_layout.hits().get(n - 1);
+                    if let Some(&(_, index)) = hit.filter(|(_, i)| *i < search_results.len()) {
+                        selection = index;
                         select_query = false;
-                    } else if !search_results.is_empty() && selection < search_results.len() - 1 {
-                        selection += 1;
+                        data.actions.push_front(Action::Execute);
                     }
                 }
                 Action::Search => {
                     need_redraw = true;
-                    search_results = apps.search(query);
+                    search_results = apps.search(&data.query);
                     select_query = false;
                     selection = 0;
                     if search_results.is_empty() {
                         select_query = true
                     }
                 }
-                Action::Complete => {
-                    if !select_query {
-                        let app = search_results.get(selection).unwrap();
-                        if query == &app.name {
-                            selection = if selection < search_results.len() - 1 {
-                                selection + 1
-                            } else {
-                                selection
-                            };
-                        }
-                        query.clear();
-                        query.push_str(&search_results.get(selection).unwrap().name);
-                        need_redraw = true;
+                Action::Complete if !select_query => {
+                    let app = search_results.get(selection).unwrap();
+                    if data.query == app.name {
+                        selection = if selection < search_results.len() - 1 {
+                            selection + 1
+                        } else {
+                            selection
+                        };
                     }
+                    data.set_query(&search_results.get(selection).unwrap().name);
+                    need_redraw = true;
                 }
                 Action::Execute => {
-                    let element = if select_query {
-                        selection::Element {
-                            name: query.to_string(),
-                            value: query.to_string(),
-                            base_score: 0,
+                    let element = match selected_element(
+                        &search_results,
+                        selection,
+                        select_query,
+                        &data.query,
+                    ) {
+                        Some(element) => element,
+                        None => {
+                            warn!("Nothing selected to execute");
+                            continue;
                         }
-                    } else {
-                        (*search_results.get(selection).unwrap()).clone()
                     };
                     if args.stdout {
                         print!("{}", element.value);
                         if let Some(mut history) = history {
-                            history.inc(&element);
+                            history.inc(&element, &data.query);
                             history.save()?;
                         }
                         return Ok(None);
                     } else {
-                        return Ok(Some(exec(element, history)?));
+                        return Ok(Some(exec(element, data.query.to_string(), history)?));
+                    }
+                }
+                Action::Custom(n) => {
+                    let custom = &config.keybindings.custom[n].1;
+                    let element = match selected_element(
+                        &search_results,
+                        selection,
+                        select_query,
+                        &data.query,
+                    ) {
+                        Some(element) => element,
+                        None => {
+                            warn!("Nothing selected to run '{}' on", custom.command);
+                            continue;
+                        }
+                    };
+                    let command = fill_template(&custom.command, &element, &data.query);
+                    if custom.close {
+                        let element = selection::Element {
+                            value: command,
+                            ..element
+                        };
+                        return Ok(Some(exec(element, data.query.to_string(), None)?));
+                    }
+                    match process::Command::new("sh")
+                        .args(["-c", &command])
+                        .stdin(process::Stdio::null())
+                        .spawn()
+                    {
+                        Ok(mut child) => {
+                            tokio::task::spawn_blocking(move || child.wait());
+                        }
+                        Err(e) => error!("Failed to run '{}': {}", command, e),
+                    }
+                }
+                Action::CopyElement => {
+                    let value = if select_query {
+                        Some(data.query.clone())
+                    } else {
+                        search_results.get(selection).map(|e| e.value.clone())
+                    };
+                    if let Some(value) = value {
+                        data.clipboard.store_primary(value.clone());
+                        data.clipboard.store(value);
+                    }
+                }
+                Action::Hover(position) => {
+                    let next = position
+                        .and_then(|(x, y)| results_layout.result_at(to_buffer(x), to_buffer(y)));
+                    if next != hover {
+                        hover = next;
+                        need_redraw = true;
+                    }
+                }
+                Action::Click { x, y, time } => {
+                    let (x, y) = (to_buffer(x), to_buffer(y));
+                    // The layout is from the last draw, the results may have changed since
+                    match results_layout
+                        .result_at(x, y)
+                        .filter(|&index| index < search_results.len())
+                    {
+                        Some(index) => {
+                            let double = last_click.replace((index, time)).is_some_and(
+                                |(last, last_time)| {
+                                    last == index && time.wrapping_sub(last_time) < DOUBLE_CLICK_MS
+                                },
+                            );
+                            selection = index;
+                            select_query = false;
+                            need_redraw = true;
+                            if config.mouse.execute == ClickMode::Click || double {
+                                data.actions.push_front(Action::Execute);
+                            }
+                        }
+                        None if config.mouse.close_on_click_outside
+                            && !content_area.contains(x, y) =>
+                        {
+                            break 'main
+                        }
+                        None => {}
+                    }
+                }
+                Action::Tap(x, y) => {
+                    let (x, y) = (to_buffer(x), to_buffer(y));
+                    match results_layout
+                        .result_at(x, y)
+                        .filter(|&index| index < search_results.len())
+                    {
+                        Some(index) => {
+                            selection = index;
+                            select_query = false;
+                            data.actions.push_front(Action::Execute);
+                        }
+                        None if config.mouse.close_on_click_outside
+                            && !content_area.contains(x, y) =>
+                        {
+                            break 'main
+                        }
+                        None => {}
+                    }
+                }
+                Action::Scroll(steps) | Action::Drag(steps) if !search_results.is_empty() => {
+                    let steps = if let Action::Drag(dy) = action {
+                        // Dragging moves the results with the finger, one row per line height
+                        let row = (config.font_size * config.style.line_height) as i32
+                            + config.style.row_spacing as i32;
+                        drag -= dy;
+                        let steps = drag / row.max(1);
+                        drag -= steps * row;
+                        steps
+                    } else {
+                        steps
+                    };
+                    if steps == 0 {
+                        continue;
+                    }
+                    let direction = if steps < 0 {
+                        Direction::Up
+                    } else {
+                        Direction::Down
+                    };
+                    for _ in 0..steps.unsigned_abs() {
+                        match results_layout.navigate(selection, direction, search_results.len()) {
+                            Some(next) => selection = next,
+                            None => break,
+                        }
                     }
+                    select_query = false;
+                    need_redraw = true;
                 }
-                Action::Exit => break,
+                Action::Reload => {
+                    need_redraw = true;
+                    let new =
+                        match Config::load(&args.config, &args.options, args.profile.as_deref()) {
+                            Ok(new) => new,
+                            Err(e) => {
+                                error!("{}", e);
+                                config_error = Some(e.to_string());
+                                continue;
+                            }
+                        };
+                    match font::Font::new(font_names(&new), new.font_size * surface.scale).await {
+                        Ok(new_font) => font = new_font,
+                        Err(e) => {
+                            error!("Failed to load fonts: {}", e);
+                            config_error = Some(format!("Failed to load fonts: {}", e));
+                            continue;
+                        }
+                    }
+                    description_size = new.font_size * new.style.description_size;
+                    description_font = font.with_size(description_size * surface.scale);
+                    background = Background::load(&new.style, &new.colors);
+                    results_layout = layout::new(new.layout, &new.grid);
+                    hover = None;
+                    data.set_keybindings(modes(&new));
+                    shape.set(window_shape(&new));
+                    surface.set_shape(shape.get());
+                    config = new;
+                    config_error = None;
+                    info!("Config reloaded");
+                }
+                Action::Exit => break 'main,
+                Action::CursorLeft
+                | Action::CursorRight
+                | Action::CursorWordLeft
+                | Action::CursorWordRight
+                | Action::CursorHome
+                | Action::CursorEnd
+                | Action::SelectLeft
+                | Action::SelectRight
+                | Action::SelectWordLeft
+                | Action::SelectWordRight
+                | Action::SelectHome
+                | Action::SelectEnd
+                | Action::SelectAll
+                | Action::Undo
+                | Action::Redo
+                | Action::Redraw => need_redraw = true,
                 _ => {}
             }
         }
 
+        let keys = data.pending_keys();
+        if keys != pending_keys {
+            pending_keys = keys;
+            need_redraw = true;
+        }
+
         if need_redraw {
             need_redraw = false;
 
-            let mut img = ImageBuffer::from_pixel(
-                surface.dimensions.0,
-                surface.dimensions.1,
-                config.colors.background.to_rgba(),
-            );
-            let prompt_width = if !config.prompt.is_empty() {
-                let (width, _) = font.render(
-                    &config.prompt,
-                    &config.colors.prompt,
-                    &mut img,
-                    config.padding,
-                    config.padding,
-                );
+            // Everything is drawn in buffer pixels
+            let padding = (config.padding as f32 * surface.scale) as u32;
+            let (width, height) = surface.physical_dimensions();
+            let mut area = layout::Area {
+                x: padding,
+                y: padding,
+                width: width.saturating_sub(2 * padding),
+                height: height.saturating_sub(2 * padding),
+            };
+
+            content_area = area;
+
+            let mut img = background.render(width, height);
+            if let Some(error) = &config_error {
+                // Below the results, which get less space
+                let line_height = description_font.line_height();
+                let lines: Vec<&str> = error.lines().collect();
+                area.height = area.height.saturating_sub(line_height * lines.len() as u32);
+                for (i, line) in lines.iter().enumerate() {
+                    description_font.render(
+                        line,
+                        &config.colors.error,
+                        &mut img,
+                        area.x,
+                        area.y + area.height + i as u32 * line_height,
+                    );
+                }
+            }
+            let mut frame = layout::Frame {
+                img: &mut img,
+                font: &mut font,
+                description_font: &mut description_font,
+                colors: &config.colors,
+                style: &config.style,
+                font_size: config.font_size * surface.scale,
+                scale: surface.scale,
+                hover,
+            };
+
+            let (before, after) = data.query.split_at(data.cursor);
+            let preedit = data.ime.preedit.as_ref();
+            // Text composed by an input method is shown at the cursor, but not part of the query yet
+            let text = match preedit {
+                Some(preedit) => format!("{}{}{}", before, preedit.text, after),
+                None => data.query.clone(),
+            };
+
+            let prompt = match data.mode_indicator() {
+                Some(indicator) => format!("{}{}", indicator, config.prompt),
+                None => config.prompt.clone(),
+            };
+            let query_width = frame.font.measure(&prompt) + frame.font.measure(&text);
+            let query_box = results_layout.query_box(&frame, area, query_width);
+            frame.query_background(query_box);
+            let text_x = query_box.x + frame.row_padding();
+            let text_y = query_box.y + frame.text_offset();
+
+            let prompt_width = if !prompt.is_empty() {
+                let (width, _) =
+                    frame
+                        .font
+                        .render(&prompt, &config.colors.prompt, frame.img, text_x, text_y);
                 width
             } else {
                 0
             };
 
-            if !query.is_empty() {
+            let query_x = text_x + prompt_width;
+            let line_height = frame.line_height();
+            let cursor_width = (frame.font_size / 16.).max(1.) as u32;
+
+            if let (Some((start, end)), None) = (data.selection(), preedit) {
+                let start_x = frame.font.measure(&data.query[..start]);
+                let end_x = frame.font.measure(&data.query[..end]);
+                draw::fill_rect(
+                    frame.img,
+                    query_x + start_x,
+                    query_box.y,
+                    end_x - start_x,
+                    line_height,
+                    &config.colors.query_selection,
+                );
+            }
+
+            if !text.is_empty() {
                 let color = if select_query {
                     &config.colors.text_selected
                 } else {
                     &config.colors.text_query
                 };
-                font.render(
-                    query,
-                    color,
-                    &mut img,
-                    config.padding + prompt_width,
-                    config.padding,
-                );
+                frame.font.render(&text, color, frame.img, query_x, text_y);
             }
 
-            let spacer = (1.5 * config.font_size) as u32;
-            let max_entries = ((surface.dimensions.1 - 2 * config.padding - spacer) as f32
-                / (config.font_size * 1.2)) as usize;
-            let offset = if selection > (max_entries / 2) {
-                (selection - max_entries / 2) as usize
-            } else {
-                0
+            // Keys of an unfinished sequence take the place of the counter
+            let status = match &pending_keys {
+                Some(keys) => Some((keys.clone(), &config.colors.prompt)),
+                None if config.style.counter => Some((
+                    format!("{}/{}", search_results.len(), total),
+                    &config.colors.counter,
+                )),
+                None => None,
             };
+            if let Some((status, color)) = status {
+                let status_width = frame.font.measure(&status);
+                let status_x = (query_box.x + query_box.width)
+                    .saturating_sub(frame.row_padding() + status_width);
+                // Left out when the query would run into it
+                if status_x > query_x + frame.font.measure(&text) + frame.font_size as u32 / 2 {
+                    frame
+                        .font
+                        .render(&status, color, frame.img, status_x, text_y);
+                }
+            }
 
-            for (i, matched) in search_results
-                .iter()
-                .enumerate()
-                .take(cmp::min(max_entries + offset, search_results.len()))
-                .skip(offset)
-            {
-                let color = if i == selection && !select_query {
-                    &config.colors.text_selected
-                } else {
-                    &config.colors.text
-                };
-                font.render(
-                    &matched.name,
-                    color,
-                    &mut img,
-                    config.padding,
-                    (config.padding
-                        + spacer
-                        + (i - offset) as u32 * (config.font_size * 1.2) as u32)
-                        as u32,
+            let cursor_x = match preedit {
+                Some(preedit) => {
+                    let start_x = frame.font.measure(before);
+                    let end_x = frame.font.measure(&format!("{}{}", before, preedit.text));
+                    draw::fill_rect(
+                        frame.img,
+                        query_x + start_x,
+                        query_box.y + line_height - cursor_width,
+                        end_x - start_x,
+                        cursor_width,
+                        &config.colors.text_query,
+                    );
+                    preedit
+                        .cursor
+                        .and_then(|(begin, _)| preedit.text.get(..begin))
+                        .map(|text| frame.font.measure(&format!("{}{}", before, text)))
+                }
+                None => Some(frame.font.measure(before)),
+            };
+            if let Some(cursor_x) = cursor_x {
+                draw::fill_rect(
+                    frame.img,
+                    query_x + cursor_x,
+                    query_box.y,
+                    cursor_width,
+                    line_height,
+                    &config.colors.text_query,
                 );
             }
+            // The input method expects surface local coordinates
+            let to_local = |v: u32| (v as f32 / surface.scale) as i32;
+            data.ime.update(
+                &data.query,
+                data.cursor,
+                data.anchor.unwrap_or(data.cursor),
+                (
+                    to_local(query_x + cursor_x.unwrap_or(0)),
+                    to_local(query_box.y),
+                    to_local(cursor_width).max(1),
+                    to_local(line_height),
+                ),
+            );
+
+            let selected = if select_query { None } else { Some(selection) };
+            results_layout.draw(&mut frame, area, query_width, &search_results, selected);
+            frame.finish();
 
             match surface.draw(img) {
                 Ok(_) => {}
@@ -307,13 +815,93 @@ async fn run() -> Result<Option<JoinHandle<()>>, Box<dyn Error>> {
         }
 
         display.flush().unwrap();
-        event_loop.dispatch(None, &mut data).unwrap();
+        event_loop.dispatch(data.key_timeout(), &mut data).unwrap();
+        data.expire_keys();
     }
     Ok(None)
 }
 
+/// Fonts of the config, with the deprecated `font` first
+fn font_names(config: &Config) -> Vec<String> {
+    let mut font_names = config.fonts.clone();
+    if let Some(font_name) = &config.font {
+        font_names.insert(0, font_name.clone());
+    }
+    font_names
+}
+
+/// Opaque and rounded parts of the window, for the compositor
+fn window_shape(config: &Config) -> gui::Shape {
+    gui::Shape {
+        opaque: Background::is_opaque(&config.colors),
+        corner_radius: config.style.border_radius,
+    }
+}
+
+/// Keybindings of the config in its editing mode
+fn modes(config: &Config) -> modes::Modes {
+    let mut keybindings = config.keybindings.clone();
+    if config.layout == Layout::Grid
+        && keybindings.nav_left.is_empty()
+        && keybindings.nav_right.is_empty()
+    {
+        // Left and right move between tiles, the cursor can still be moved with home and end
+        keybindings.nav_left = std::mem::take(&mut keybindings.cursor_left);
+        keybindings.nav_right = std::mem::take(&mut keybindings.cursor_right);
+    }
+    modes::Modes::new(keybindings.mode, keybindings.into())
+}
+
+/// Longest time between two clicks on the same result that counts as double click
+const DOUBLE_CLICK_MS: u32 = 400;
+
+/// The selected result, or the query itself if it is selected
+fn selected_element(
+    results: &[&selection::Element],
+    selection: usize,
+    select_query: bool,
+    query: &str,
+) -> Option<selection::Element> {
+    if select_query {
+        Some(selection::Element {
+            name: query.to_string(),
+            value: query.to_string(),
+            description: None,
+            base_score: 0,
+        })
+    } else {
+        results.get(selection).map(|&element| element.clone())
+    }
+}
+
+/// Puts the quoted value, name and query of `element` into a custom command
+fn fill_template(template: &str, element: &selection::Element, query: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+    // Replaced in one pass, so placeholders in the inserted values are left alone
+    while let Some(start) = rest.find('{') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (replacement, len) = if rest.starts_with("{value}") {
+            (quote(&element.value), "{value}".len())
+        } else if rest.starts_with("{name}") {
+            (quote(&element.name), "{name}".len())
+        } else if rest.starts_with("{query}") {
+            (quote(query), "{query}".len())
+        } else {
+            ("{".to_owned(), 1)
+        };
+        res.push_str(&replacement);
+        rest = &rest[len..];
+    }
+    res.push_str(rest);
+    res
+}
+
 fn exec(
     elem: selection::Element,
+    query: String,
     history: Option<History>,
 ) -> Result<tokio::task::JoinHandle<()>, Box<dyn Error>> {
     match unsafe { fork() } {
@@ -323,7 +911,7 @@ fn exec(
                 match waitpid(child, Some(WaitPidFlag::WNOHANG)) {
                     Ok(WaitStatus::StillAlive) | Ok(WaitStatus::Exited(_, 0)) => {
                         if let Some(mut history) = history {
-                            history.inc(&elem);
+                            history.inc(&elem, &query);
                             match history.save() {
                                 Ok(()) => {}
                                 Err(e) => {
@@ -0,0 +1,150 @@
+use smithay_client_toolkit::seat::keyboard::{keysyms, ModifiersState};
+use std::time::Duration;
+
+use crate::config::EditingMode;
+use crate::gui::Action;
+use crate::keybinds::{latin_keysym, Keybindings, Outcome};
+
+const NORMAL_INDICATOR: &str = "[N] ";
+const INSERT_INDICATOR: &str = "[I] ";
+
+enum Mode {
+    Default,
+    /// Vi insert mode, keys are typed as usual
+    Insert,
+    /// Vi normal mode, keys are commands
+    Normal,
+}
+
+/// Editing mode on top of the configured keybindings, which keeps the state
+/// needed for modal editing and multi key commands like `dd`
+pub struct Modes {
+    keybindings: Keybindings,
+    mode: Mode,
+    /// First key of a vi command waiting for the second one
+    pending: Option<u32>,
+}
+
+impl Modes {
+    pub fn new(mode: EditingMode, keybindings: Keybindings) -> Self {
+        Modes {
+            keybindings,
+            mode: match mode {
+                // The emacs bindings are part of the keybindings
+                EditingMode::Default | EditingMode::Emacs => Mode::Default,
+                // A launcher is opened to type, so vi starts in insert mode
+                EditingMode::Vi => Mode::Insert,
+            },
+            pending: None,
+        }
+    }
+
+    /// Shown in front of the prompt
+    pub fn indicator(&self) -> Option<&'static str> {
+        match self.mode {
+            Mode::Normal => Some(NORMAL_INDICATOR),
+            Mode::Insert => Some(INSERT_INDICATOR),
+            Mode::Default => None,
+        }
+    }
+
+    /// What a key press does, `None` if the key should be typed into the query
+    pub fn get(
+        &mut self,
+        modifiers: &ModifiersState,
+        keysym: u32,
+        keycode: u32,
+        utf8: Option<&str>,
+    ) -> Option<Outcome> {
+        let plain = !modifiers.ctrl && !modifiers.alt && !modifiers.logo;
+        // The commands of the modes stay on the same keys with non latin layouts
+        let command = latin_keysym(keysym, keycode, modifiers.shift).unwrap_or(keysym);
+        if self.keybindings.pending().is_some() {
+            // The rest of a key sequence
+            let outcome = self.keybindings.get(modifiers, keysym, keycode, utf8);
+            return match self.mode {
+                Mode::Normal => Some(commands(outcome)),
+                _ => outcome,
+            };
+        }
+        match self.mode {
+            Mode::Insert if plain && keysym == keysyms::XKB_KEY_Escape => {
+                self.mode = Mode::Normal;
+                return Some(vec![Action::Redraw].into());
+            }
+            Mode::Normal => {
+                let pending = self.pending.take();
+                if plain {
+                    if let Some(actions) = self.normal(pending, command) {
+                        return Some(actions.into());
+                    }
+                }
+                // Everything else is a command as well
+                let outcome = self.keybindings.get(modifiers, keysym, keycode, utf8);
+                return Some(commands(outcome));
+            }
+            Mode::Default | Mode::Insert => {}
+        }
+        self.keybindings.get(modifiers, keysym, keycode, utf8)
+    }
+
+    /// Keys of an unfinished sequence
+    pub fn pending(&self) -> Option<String> {
+        self.keybindings.pending()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.keybindings.timeout()
+    }
+
+    pub fn expire(&mut self) -> Option<Outcome> {
+        let outcome = self.keybindings.expire();
+        match self.mode {
+            Mode::Normal => outcome.map(|outcome| commands(Some(outcome))),
+            _ => outcome,
+        }
+    }
+
+    fn normal(&mut self, pending: Option<u32>, keysym: u32) -> Option<Vec<Action>> {
+        let actions = match (pending, keysym) {
+            (Some(keysyms::XKB_KEY_d), keysyms::XKB_KEY_d) => {
+                vec![Action::KillToStart, Action::KillToEnd]
+            }
+            (Some(keysyms::XKB_KEY_g), keysyms::XKB_KEY_g) => vec![Action::First],
+            (_, keysyms::XKB_KEY_d | keysyms::XKB_KEY_g) => {
+                self.pending = Some(keysym);
+                vec![]
+            }
+            (_, keysyms::XKB_KEY_j) => vec![Action::NavDown],
+            (_, keysyms::XKB_KEY_k) => vec![Action::NavUp],
+            (_, keysyms::XKB_KEY_h) => vec![Action::CursorLeft],
+            (_, keysyms::XKB_KEY_l) => vec![Action::CursorRight],
+            (_, keysyms::XKB_KEY_w) => vec![Action::CursorWordRight],
+            (_, keysyms::XKB_KEY_b) => vec![Action::CursorWordLeft],
+            (_, keysyms::XKB_KEY_0) => vec![Action::CursorHome],
+            (_, keysyms::XKB_KEY_dollar) => vec![Action::CursorEnd],
+            (_, keysyms::XKB_KEY_x) => vec![Action::DeleteForward],
+            (_, keysyms::XKB_KEY_G) => vec![Action::Last],
+            (_, keysyms::XKB_KEY_i) => self.insert(Action::Redraw),
+            (_, keysyms::XKB_KEY_a) => self.insert(Action::CursorRight),
+            (_, keysyms::XKB_KEY_A) => self.insert(Action::CursorEnd),
+            (_, keysyms::XKB_KEY_I) => self.insert(Action::CursorHome),
+            _ => return None,
+        };
+        Some(actions)
+    }
+
+    fn insert(&mut self, action: Action) -> Vec<Action> {
+        self.mode = Mode::Insert;
+        // The indicator changes even if the cursor does not move
+        vec![action, Action::Redraw]
+    }
+}
+
+/// Only the actions of `outcome`, text is never typed in normal mode
+fn commands(outcome: Option<Outcome>) -> Outcome {
+    outcome
+        .map(|outcome| outcome.actions)
+        .unwrap_or_default()
+        .into()
+}
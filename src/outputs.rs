@@ -0,0 +1,105 @@
+use log::*;
+use smithay_client_toolkit::{
+    output::with_output_info,
+    reexports::{
+        client::{protocol::wl_output::WlOutput, Attached, EventQueue},
+        protocols::unstable::xdg_output::v1::client::{
+            zxdg_output_manager_v1::ZxdgOutputManagerV1, zxdg_output_v1,
+        },
+    },
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Selects the output of the compositors choice, which usually is the focused one
+pub const FOCUSED: &str = "focused";
+
+#[derive(Default, Clone, Debug)]
+struct OutputNames {
+    name: String,
+    description: String,
+}
+
+/// Finds the output matching `selector`, either by its exact name (i.e. `DP-1`)
+/// or a part of its description. Returns `None` for the focused output.
+pub fn find(
+    outputs: Vec<WlOutput>,
+    xdg_output_manager: Option<Attached<ZxdgOutputManagerV1>>,
+    queue: &mut EventQueue,
+    selector: &str,
+) -> Option<WlOutput> {
+    if selector.is_empty() || selector == FOCUSED {
+        return None;
+    }
+
+    let names = query_names(&outputs, xdg_output_manager, queue);
+    let selector_lower = selector.to_lowercase();
+    let found = outputs
+        .iter()
+        .zip(names.iter())
+        .find(|(_, names)| names.name == selector)
+        .or_else(|| {
+            outputs
+                .iter()
+                .zip(names.iter())
+                .find(|(_, names)| names.description.to_lowercase().contains(&selector_lower))
+        })
+        .map(|(output, _)| output.clone());
+
+    if found.is_none() {
+        let available: Vec<&str> = names.iter().map(|n| n.name.as_str()).collect();
+        warn!(
+            "No output matching '{}' found, using the focused one. Available: {}",
+            selector,
+            available.join(", ")
+        );
+    }
+    found
+}
+
+/// Names are only advertised through xdg-output, fall back to make and model without it
+fn query_names(
+    outputs: &[WlOutput],
+    xdg_output_manager: Option<Attached<ZxdgOutputManagerV1>>,
+    queue: &mut EventQueue,
+) -> Vec<OutputNames> {
+    let names: Vec<Rc<RefCell<OutputNames>>> = outputs
+        .iter()
+        .map(|output| {
+            let names = with_output_info(output, |info| OutputNames {
+                name: info.name.clone(),
+                description: format!("{} {} {}", info.make, info.model, info.description),
+            })
+            .unwrap_or_default();
+            Rc::new(RefCell::new(names))
+        })
+        .collect();
+
+    if let Some(manager) = xdg_output_manager {
+        let xdg_outputs: Vec<_> = outputs
+            .iter()
+            .zip(names.iter())
+            .map(|(output, names)| {
+                let names = Rc::clone(names);
+                let xdg_output = manager.get_xdg_output(output);
+                xdg_output.quick_assign(move |_, event, _| match event {
+                    zxdg_output_v1::Event::Name { name } => names.borrow_mut().name = name,
+                    zxdg_output_v1::Event::Description { description } => {
+                        names.borrow_mut().description = description
+                    }
+                    _ => {}
+                });
+                xdg_output
+            })
+            .collect();
+
+        if let Err(e) = queue.sync_roundtrip(&mut (), |_, _, _| {}) {
+            error!("Failed to query output names: {}", e);
+        }
+        xdg_outputs
+            .iter()
+            .for_each(|xdg_output| xdg_output.destroy());
+    }
+
+    names.iter().map(|names| names.borrow().clone()).collect()
+}
@@ -0,0 +1,159 @@
+use smithay_client_toolkit::{
+    reexports::client::{
+        protocol::{
+            wl_pointer::{self, Axis, ButtonState},
+            wl_seat,
+            wl_touch::{self, WlTouch},
+        },
+        Attached, DispatchData, Main,
+    },
+    seat::with_seat_data,
+};
+
+use crate::gui::{Action, DData};
+
+const BTN_LEFT: u32 = 272;
+const BTN_MIDDLE: u32 = 274;
+/// Scroll distance of one wheel step without discrete axis events
+const WHEEL_STEP: f64 = 10.;
+/// Distance a finger has to move before a touch counts as drag instead of tap
+const TAP_SLOP: f64 = 10.;
+
+/// State of pointers and touch points across all seats, in surface local coordinates
+#[derive(Default)]
+pub struct Pointer {
+    position: (f64, f64),
+    scroll: f64,
+    /// Wheel steps have already been reported for the current pointer frame
+    discrete: bool,
+    touch: Option<Touch>,
+}
+
+struct Touch {
+    id: i32,
+    start: (f64, f64),
+    /// Last position reported as drag
+    y: f64,
+    dragging: bool,
+}
+
+pub fn register(seats: &[Attached<wl_seat::WlSeat>]) {
+    for seat in seats {
+        let (has_pointer, has_touch) = with_seat_data(seat, |seat_data| {
+            (
+                seat_data.has_pointer && !seat_data.defunct,
+                seat_data.has_touch && !seat_data.defunct,
+            )
+        })
+        .unwrap_or_default();
+        if has_pointer {
+            let pointer = seat.get_pointer();
+            pointer.quick_assign(move |_, event, ddata| process_pointer_event(event, ddata));
+        }
+        if has_touch {
+            let touch: Main<WlTouch> = seat.get_touch();
+            touch.quick_assign(move |_, event, ddata| process_touch_event(event, ddata));
+        }
+    }
+}
+
+fn process_pointer_event(event: wl_pointer::Event, mut data: DispatchData) {
+    let data = data.get::<DData>().unwrap();
+    let pointer = &mut data.pointer;
+    match event {
+        wl_pointer::Event::Enter {
+            surface_x,
+            surface_y,
+            ..
+        }
+        | wl_pointer::Event::Motion {
+            surface_x,
+            surface_y,
+            ..
+        } => {
+            pointer.position = (surface_x, surface_y);
+            data.push_action(Action::Hover(Some((surface_x as i32, surface_y as i32))));
+        }
+        wl_pointer::Event::Leave { .. } => data.push_action(Action::Hover(None)),
+        wl_pointer::Event::Button {
+            button: BTN_LEFT,
+            state: ButtonState::Pressed,
+            time,
+            ..
+        } => {
+            let (x, y) = pointer.position;
+            data.push_action(Action::Click {
+                x: x as i32,
+                y: y as i32,
+                time,
+            });
+        }
+        wl_pointer::Event::Button {
+            button: BTN_MIDDLE,
+            state: ButtonState::Pressed,
+            ..
+        } => {
+            if let Ok(txt) = data.clipboard.load_primary() {
+                data.insert(&txt);
+                data.push_action(Action::Search);
+            }
+        }
+        wl_pointer::Event::AxisDiscrete {
+            axis: Axis::VerticalScroll,
+            discrete,
+        } => {
+            pointer.discrete = true;
+            data.push_action(Action::Scroll(discrete));
+        }
+        wl_pointer::Event::Axis {
+            axis: Axis::VerticalScroll,
+            value,
+            ..
+        } if !pointer.discrete => {
+            // Smooth scrolling, i.e. on touchpads
+            pointer.scroll += value;
+            let steps = (pointer.scroll / WHEEL_STEP).trunc();
+            if steps != 0. {
+                pointer.scroll -= steps * WHEEL_STEP;
+                data.push_action(Action::Scroll(steps as i32));
+            }
+        }
+        wl_pointer::Event::Frame => pointer.discrete = false,
+        _ => {}
+    }
+}
+
+fn process_touch_event(event: wl_touch::Event, mut data: DispatchData) {
+    let data = data.get::<DData>().unwrap();
+    match event {
+        // Only the first finger is followed
+        wl_touch::Event::Down { id, x, y, .. } if data.pointer.touch.is_none() => {
+            data.pointer.touch = Some(Touch {
+                id,
+                start: (x, y),
+                y,
+                dragging: false,
+            });
+        }
+        wl_touch::Event::Motion { id, y, .. } => {
+            if let Some(touch) = data.pointer.touch.as_mut().filter(|touch| touch.id == id) {
+                touch.dragging |= (y - touch.start.1).abs() > TAP_SLOP;
+                let dy = (y - touch.y).trunc();
+                if touch.dragging && dy != 0. {
+                    touch.y += dy;
+                    data.push_action(Action::Drag(dy as i32));
+                }
+            }
+        }
+        wl_touch::Event::Up { id, .. } => {
+            if let Some(touch) = data.pointer.touch.take_if(|touch| touch.id == id) {
+                if !touch.dragging {
+                    let (x, y) = touch.start;
+                    data.push_action(Action::Tap(x as i32, y as i32));
+                }
+            }
+        }
+        wl_touch::Event::Cancel => data.pointer.touch = None,
+        _ => {}
+    }
+}
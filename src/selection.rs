@@ -1,3 +1,5 @@
+use crate::aliases;
+use crate::desktop;
 use crate::history::History;
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use log::*;
@@ -26,6 +28,8 @@ use tokio::{
 pub struct Element {
     pub name: String,
     pub value: String,
+    /// Additional context shown next to the name
+    pub description: Option<String>,
     pub base_score: usize,
 }
 
@@ -40,10 +44,7 @@ impl Ord for Element {
 
 impl PartialOrd for Element {
     fn partial_cmp(&self, other: &Element) -> Option<Ordering> {
-        match other.base_score.cmp(&self.base_score) {
-            Ordering::Equal => Some(self.name.cmp(&other.name)),
-            e => Some(e),
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -61,6 +62,7 @@ impl ElementList {
                 self.inner.push(Element {
                     name: entry.name.to_owned(),
                     value: entry.value.to_owned(),
+                    description: None,
                     base_score: entry.num_used,
                 })
             }
@@ -68,7 +70,7 @@ impl ElementList {
     }
 
     pub fn sort_score(&mut self) {
-        self.inner.sort_by(|a, b| b.base_score.cmp(&a.base_score))
+        self.inner.sort_by_key(|e| std::cmp::Reverse(e.base_score))
     }
 
     pub fn search(&self, pattern: &str) -> Vec<&Element> {
@@ -86,7 +88,7 @@ impl ElementList {
             })
             .filter(|x| x.0.is_some())
             .collect::<Vec<(Option<i64>, &Element)>>();
-        executables.sort_by(|a, b| b.0.unwrap_or(0).cmp(&a.0.unwrap_or(0)));
+        executables.sort_by_key(|x| std::cmp::Reverse(x.0.unwrap_or(0)));
         executables.into_iter().map(|x| x.1).collect()
     }
 
@@ -100,6 +102,8 @@ pub struct ElementListBuilder {
     from_path: bool,
     from_stdin: bool,
     from_file: Vec<PathBuf>,
+    from_desktop: bool,
+    from_aliases: Option<Option<PathBuf>>,
 }
 
 impl ElementListBuilder {
@@ -116,6 +120,13 @@ impl ElementListBuilder {
     pub fn add_stdin(&mut self) {
         self.from_stdin = true;
     }
+    pub fn add_desktop(&mut self) {
+        self.from_desktop = true;
+    }
+    /// Adds shell aliases and functions, either read from `alias_file` or from `$SHELL`
+    pub fn add_aliases(&mut self, alias_file: Option<PathBuf>) {
+        self.from_aliases = Some(alias_file);
+    }
 
     pub async fn build(&self) -> Result<ElementList, Box<dyn std::error::Error>> {
         let mut fut = Vec::new();
@@ -131,6 +142,13 @@ impl ElementListBuilder {
         if self.from_path {
             fut.push(spawn_blocking(ElementListBuilder::build_path))
         }
+        if self.from_desktop {
+            fut.push(spawn_blocking(desktop::load))
+        }
+        let aliases = self
+            .from_aliases
+            .clone()
+            .map(|alias_file| spawn_blocking(move || aliases::load(alias_file.as_deref())));
 
         let finished = futures::future::join_all(fut).await;
 
@@ -139,6 +157,9 @@ impl ElementListBuilder {
             let mut elements = elements??;
             res.append(&mut elements);
         }
+        if let Some(aliases) = aliases {
+            aliases::shadow(&mut res, aliases.await??);
+        }
 
         Ok(ElementList { inner: res })
     }
@@ -149,7 +170,8 @@ impl ElementListBuilder {
             let mut reader = BufReader::new(File::open(file)?);
             let mut buf = String::new();
             while reader.read_line(&mut buf)? > 0 {
-                let kv_pair = match parse_line(&buf) {
+                let (line, description) = split_description(&buf);
+                let kv_pair = match parse_line(line) {
                     Ok(None) => continue,
                     Ok(Some(res)) => res,
                     Err(e) => {
@@ -161,12 +183,14 @@ impl ElementListBuilder {
                     (key, Some(value)) => res.push(Element {
                         name: key.to_string(),
                         value: value.to_string(),
+                        description,
                         base_score: 0,
                     }),
                     ("", None) => {} // Empty Line
                     (key, None) => res.push(Element {
                         name: key.to_string(),
                         value: key.to_string(),
+                        description,
                         base_score: 0,
                     }),
                 }
@@ -194,6 +218,7 @@ impl ElementListBuilder {
                         res.push(Element {
                             value: name.clone(),
                             name,
+                            description: None,
                             base_score: 0,
                         });
                     }
@@ -214,7 +239,8 @@ impl ElementListBuilder {
         let mut res = Vec::new();
 
         while let Some(line) = lines.next_line().await? {
-            let kv_pair = match parse_line(&line) {
+            let (line, description) = split_description(&line);
+            let kv_pair = match parse_line(line) {
                 Ok(None) => continue,
                 Ok(Some(res)) => res,
                 Err(e) => {
@@ -226,12 +252,14 @@ impl ElementListBuilder {
                 (key, Some(value)) => res.push(Element {
                     name: key.to_string(),
                     value: value.to_string(),
+                    description,
                     base_score: 0,
                 }),
                 ("", None) => {} // Empty Line
                 (key, None) => res.push(Element {
                     name: key.to_string(),
                     value: key.to_string(),
+                    description,
                     base_score: 0,
                 }),
             }
@@ -241,10 +269,21 @@ impl ElementListBuilder {
     }
 }
 
+/// A description can follow the entry, separated by a tab: `name=value\tdescription`
+fn split_description(line: &str) -> (&str, Option<String>) {
+    match line.trim_end().split_once('\t') {
+        Some((entry, description)) if !description.trim().is_empty() => {
+            (entry, Some(description.trim().to_owned()))
+        }
+        Some((entry, _)) => (entry, None),
+        None => (line, None),
+    }
+}
+
 #[allow(clippy::type_complexity)]
 fn parse_line<'a>(
     input: &'a str,
-) -> Result<Option<(&str, Option<&str>)>, Box<dyn std::error::Error + 'a>> {
+) -> Result<Option<(&'a str, Option<&'a str>)>, Box<dyn std::error::Error + 'a>> {
     let input = input.trim_end();
     match pair(
         alt((is_not("\"="), quoted_string)),
@@ -0,0 +1,99 @@
+use log::*;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+use toml::value::{Table, Value};
+use xdg::BaseDirectories;
+
+const BUILTIN: [(&str, &str); 5] = [
+    ("one-dark", include_str!("../assets/themes/one-dark.toml")),
+    ("gruvbox", include_str!("../assets/themes/gruvbox.toml")),
+    (
+        "solarized-dark",
+        include_str!("../assets/themes/solarized-dark.toml"),
+    ),
+    (
+        "solarized-light",
+        include_str!("../assets/themes/solarized-light.toml"),
+    ),
+    ("nord", include_str!("../assets/themes/nord.toml")),
+];
+
+/// Config sections a theme is allowed to set
+const SECTIONS: [&str; 2] = ["colors", "style"];
+
+/// Fills in the colors and style of the theme named in `config`,
+/// values set in the config itself take precedence.
+pub fn apply(config: &mut Table) -> Result<(), Box<dyn Error>> {
+    let name = match config.get("theme") {
+        Some(Value::String(name)) => name.clone(),
+        Some(_) => return Err("theme has to be a name or path".into()),
+        None => return Ok(()),
+    };
+    for (section, values) in load(&name)? {
+        let values = match values {
+            Value::Table(values) => values,
+            _ => return Err(format!("'{}' in theme {} has to be a table", section, name).into()),
+        };
+        match config.get_mut(&section) {
+            Some(Value::Table(own)) => {
+                for (key, value) in values {
+                    own.entry(key).or_insert(value);
+                }
+            }
+            Some(_) => {}
+            None => {
+                config.insert(section, Value::Table(values));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Looks for a theme by path, then in `$XDG_CONFIG_HOME/kickoff/themes/` and
+/// finally among the built-in themes
+fn load(name: &str) -> Result<Table, Box<dyn Error>> {
+    let xdg_dirs = BaseDirectories::with_prefix("kickoff")?;
+    let content = if name.contains('/') || name.ends_with(".toml") {
+        read_to_string(Path::new(name))
+            .map_err(|e| format!("Failed to read theme {}: {}", name, e))?
+    } else if let Some(path) = xdg_dirs.find_config_file(format!("themes/{}.toml", name)) {
+        read_to_string(path)?
+    } else if let Some((_, content)) = BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+        content.to_string()
+    } else {
+        return Err(format!("Unknown theme '{}', see kickoff --list-themes", name).into());
+    };
+
+    let theme: Table =
+        toml::from_str(&content).map_err(|e| format!("Invalid theme {}: {}", name, e))?;
+    let theme = theme
+        .into_iter()
+        .filter(|(key, _)| {
+            let known = SECTIONS.contains(&key.as_str());
+            if !known {
+                warn!(
+                    "Ignoring '{}' in theme {}, themes only set colors and style",
+                    key, name
+                );
+            }
+            known
+        })
+        .collect();
+    Ok(theme)
+}
+
+/// Names of the built-in themes, followed by those of the user with their path
+pub fn list() -> Vec<String> {
+    let mut themes: Vec<String> = BUILTIN.iter().map(|(name, _)| name.to_string()).collect();
+    if let Ok(xdg_dirs) = BaseDirectories::with_prefix("kickoff") {
+        for path in xdg_dirs.list_config_files("themes") {
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                if let Some(name) = path.file_stem() {
+                    themes.push(format!("{} ({})", name.to_string_lossy(), path.display()));
+                }
+            }
+        }
+    }
+    themes
+}
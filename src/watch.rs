@@ -0,0 +1,49 @@
+use log::*;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use smithay_client_toolkit::reexports::calloop::{
+    self, generic::Generic, Interest, Mode, PostAction,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::gui::{Action, DData};
+
+/// Asks for a reload whenever a toml file in one of `dirs` has been written.
+/// Directories are watched instead of the files, as editors often save by
+/// replacing the file.
+pub fn register(
+    dirs: &[PathBuf],
+    event_loop: &calloop::EventLoop<DData>,
+) -> Result<(), Box<dyn Error>> {
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+    for dir in dirs {
+        if let Err(e) = inotify.add_watch(
+            dir.as_path(),
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+        ) {
+            warn!("Can't watch {} for config changes: {}", dir.display(), e);
+        }
+    }
+    event_loop
+        .handle()
+        .insert_source(
+            Generic::new(inotify, Interest::READ, Mode::Level),
+            |_, inotify, data| {
+                let changed = inotify
+                    .read_events()
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|event| {
+                        event.name.as_ref().is_some_and(|name| {
+                            Path::new(name).extension() == Some("toml".as_ref())
+                        })
+                    });
+                if changed {
+                    data.push_action(Action::Reload);
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}